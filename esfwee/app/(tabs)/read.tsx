@@ -51,6 +51,7 @@ export default function ReadScreen() {
       setLocalManga(manga);
       const newCovers: Record<number, string> = {};
       for (const m of manga) {
+        if (m.anilist_id === null) continue;
         const { data } = await getMangaDetails({
           variables: { id: m.anilist_id },
         });
@@ -81,7 +82,7 @@ export default function ReadScreen() {
     try {
       if (!esfweeUrl) return;
       const client = new MangaApiClient(esfweeUrl);
-      const chapterList = await client.listChapters(manga.anilist_id);
+      const chapterList = await client.listChapters(manga.id);
       setChapters(chapterList);
       setViewMode("chapters");
     } catch (error) {
@@ -191,7 +192,7 @@ export default function ReadScreen() {
                 />
               </TouchableOpacity>
             )}
-            keyExtractor={(item) => item.anilist_id.toString()}
+            keyExtractor={(item) => item.id.toString()}
             showsVerticalScrollIndicator={false}
             contentContainerStyle={{
               paddingHorizontal: 16,
@@ -81,7 +81,8 @@ export default function MangaDetailScreen() {
     if (!esfweeUrl || !id) return;
     try {
       const client = new MangaApiClient(esfweeUrl);
-      const chapters = await client.listChapters(parseInt(id));
+      const series = await client.getManga(parseInt(id));
+      const chapters = await client.listChapters(series.id);
       setLocalChapters(chapters.length);
     } catch (error) {
       setLocalChapters(0);
@@ -32,7 +32,7 @@ export default function ChaptersScreen() {
         const client = new MangaApiClient(esfweeUrl);
         const manga = await client.getManga(parseInt(id));
         setMangaTitle(manga.title);
-        const chapterList = await client.listChapters(parseInt(id));
+        const chapterList = await client.listChapters(manga.id);
         setChapters(
           chapterList.sort((a, b) => a.chapter_number - b.chapter_number),
         );
@@ -1,5 +1,6 @@
 export interface Manga {
-  anilist_id: number;
+  id: number;
+  anilist_id: number | null;
   title: string;
   author?: string;
   description?: string;
@@ -10,7 +11,7 @@ export interface Manga {
 
 export interface Chapter {
   id: number;
-  anilist_id: number;
+  manga_id: number;
   chapter_number: number;
   title?: string;
   page_count: number;
@@ -88,19 +89,22 @@ export class MangaApiClient {
   }
 
   async getManga(anilistId: number): Promise<Manga> {
-    const response = await fetch(`${this.baseUrl}/manga/${anilistId}`);
+    const response = await fetch(`${this.baseUrl}/manga/anilist/${anilistId}`);
     return this.handleResponse<Manga>(response);
   }
 
   async deleteManga(anilistId: number): Promise<void> {
-    const response = await fetch(`${this.baseUrl}/manga/${anilistId}`, {
-      method: "DELETE",
-    });
+    const response = await fetch(
+      `${this.baseUrl}/manga/anilist/${anilistId}`,
+      {
+        method: "DELETE",
+      },
+    );
     return this.handleResponse<void>(response);
   }
 
-  async listChapters(anilistId: number): Promise<Chapter[]> {
-    const response = await fetch(`${this.baseUrl}/manga/${anilistId}/chapters`);
+  async listChapters(mangaId: number): Promise<Chapter[]> {
+    const response = await fetch(`${this.baseUrl}/manga/${mangaId}/chapters`);
     return this.handleResponse<Chapter[]>(response);
   }
 
@@ -0,0 +1,15 @@
+CREATE TABLE download_jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    anilist_id INTEGER NOT NULL,
+    chapter_url TEXT NOT NULL,
+    chapter_number REAL NOT NULL,
+    chapter_title TEXT,
+    status TEXT NOT NULL DEFAULT 'queued',
+    pages_done INTEGER NOT NULL DEFAULT 0,
+    pages_total INTEGER,
+    error TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_download_jobs_status ON download_jobs(status);
@@ -0,0 +1 @@
+ALTER TABLE download_jobs ADD COLUMN source TEXT NOT NULL DEFAULT 'mangapill';
@@ -0,0 +1,10 @@
+CREATE TABLE reading_progress (
+    anilist_id INTEGER NOT NULL REFERENCES manga(anilist_id) ON DELETE CASCADE,
+    chapter_id INTEGER NOT NULL REFERENCES chapters(id) ON DELETE CASCADE,
+    page INTEGER NOT NULL DEFAULT 1,
+    completed BOOLEAN NOT NULL DEFAULT 0,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (anilist_id, chapter_id)
+);
+
+CREATE INDEX idx_reading_progress_updated ON reading_progress(updated_at);
@@ -0,0 +1,20 @@
+-- The one AniList account progress is pushed to
+CREATE TABLE anilist_account (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    access_token TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    user_name TEXT NOT NULL,
+    added_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE anilist_sync (
+    anilist_id INTEGER PRIMARY KEY REFERENCES manga(anilist_id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'pending',
+    progress INTEGER,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    synced_at DATETIME
+);
+
+CREATE INDEX idx_anilist_sync_status ON anilist_sync(status);
@@ -0,0 +1,13 @@
+-- The linked account's MANGA list as last pulled from AniList. Entries need not be in the library.
+CREATE TABLE anilist_list (
+    anilist_id INTEGER PRIMARY KEY,
+    title TEXT NOT NULL,
+    status TEXT NOT NULL,
+    progress INTEGER NOT NULL DEFAULT 0,
+    score REAL,
+    -- Unix timestamp of the entry's last change on AniList
+    anilist_updated_at INTEGER,
+    pulled_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_anilist_list_status ON anilist_list(status);
@@ -0,0 +1,13 @@
+-- List columns hold JSON arrays of strings
+ALTER TABLE manga ADD COLUMN native_title TEXT;
+ALTER TABLE manga ADD COLUMN synonyms TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE manga ADD COLUMN genres TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE manga ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE manga ADD COLUMN status TEXT;
+ALTER TABLE manga ADD COLUMN total_chapters INTEGER;
+ALTER TABLE manga ADD COLUMN total_volumes INTEGER;
+ALTER TABLE manga ADD COLUMN start_year INTEGER;
+ALTER TABLE manga ADD COLUMN cover_url TEXT;
+ALTER TABLE manga ADD COLUMN cover_path TEXT;
+ALTER TABLE manga ADD COLUMN banner_url TEXT;
+ALTER TABLE manga ADD COLUMN banner_path TEXT;
@@ -0,0 +1,102 @@
+-- Series get their own id so ones AniList doesn't list can be stored too; `anilist_id`
+-- becomes an optional link. Existing series keep their AniList id as their id, so
+-- storage paths and client bookmarks stay valid.
+--
+-- Migrations run in a transaction where foreign keys can't be switched off, so the old
+-- tables are renamed out of the way first (which repoints references at them) and only
+-- dropped once nothing refers to them, keeping ON DELETE CASCADE from firing.
+ALTER TABLE manga RENAME TO manga_old;
+ALTER TABLE chapters RENAME TO chapters_old;
+ALTER TABLE reading_progress RENAME TO reading_progress_old;
+ALTER TABLE anilist_sync RENAME TO anilist_sync_old;
+
+CREATE TABLE manga (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    anilist_id INTEGER UNIQUE,
+    title TEXT NOT NULL,
+    author TEXT,
+    description TEXT,
+    storage_path TEXT NOT NULL,
+    added_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    native_title TEXT,
+    synonyms TEXT NOT NULL DEFAULT '[]',
+    genres TEXT NOT NULL DEFAULT '[]',
+    tags TEXT NOT NULL DEFAULT '[]',
+    status TEXT,
+    total_chapters INTEGER,
+    total_volumes INTEGER,
+    start_year INTEGER,
+    cover_url TEXT,
+    cover_path TEXT,
+    banner_url TEXT,
+    banner_path TEXT
+);
+
+INSERT INTO manga (id, anilist_id, title, author, description, storage_path, added_at, updated_at,
+                   native_title, synonyms, genres, tags, status, total_chapters, total_volumes,
+                   start_year, cover_url, cover_path, banner_url, banner_path)
+SELECT anilist_id, anilist_id, title, author, description, storage_path, added_at, updated_at,
+       native_title, synonyms, genres, tags, status, total_chapters, total_volumes,
+       start_year, cover_url, cover_path, banner_url, banner_path
+FROM manga_old;
+
+-- Downloads used to create their series when they finished. Those still waiting get a
+-- stand-in row to land in, which the metadata refresh fills in from AniList.
+INSERT INTO manga (id, anilist_id, title, storage_path)
+SELECT DISTINCT anilist_id, anilist_id, 'AniList ' || anilist_id, 'data/manga/' || anilist_id
+FROM download_jobs
+WHERE status != 'completed' AND anilist_id NOT IN (SELECT id FROM manga);
+
+CREATE TABLE chapters (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    manga_id INTEGER NOT NULL REFERENCES manga(id) ON DELETE CASCADE,
+    chapter_number REAL NOT NULL,
+    title TEXT,
+    page_count INTEGER NOT NULL,
+    storage_path TEXT NOT NULL,
+    added_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    UNIQUE(manga_id, chapter_number)
+);
+
+INSERT INTO chapters (id, manga_id, chapter_number, title, page_count, storage_path, added_at)
+SELECT id, anilist_id, chapter_number, title, page_count, storage_path, added_at
+FROM chapters_old;
+
+CREATE TABLE reading_progress (
+    manga_id INTEGER NOT NULL REFERENCES manga(id) ON DELETE CASCADE,
+    chapter_id INTEGER NOT NULL REFERENCES chapters(id) ON DELETE CASCADE,
+    page INTEGER NOT NULL DEFAULT 1,
+    completed BOOLEAN NOT NULL DEFAULT 0,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (manga_id, chapter_id)
+);
+
+INSERT INTO reading_progress (manga_id, chapter_id, page, completed, updated_at)
+SELECT anilist_id, chapter_id, page, completed, updated_at
+FROM reading_progress_old;
+
+CREATE TABLE anilist_sync (
+    manga_id INTEGER PRIMARY KEY REFERENCES manga(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'pending',
+    progress INTEGER,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    synced_at DATETIME
+);
+
+INSERT INTO anilist_sync (manga_id, status, progress, attempts, error, updated_at, synced_at)
+SELECT anilist_id, status, progress, attempts, error, updated_at, synced_at
+FROM anilist_sync_old;
+
+DROP TABLE anilist_sync_old;
+DROP TABLE reading_progress_old;
+DROP TABLE chapters_old;
+DROP TABLE manga_old;
+
+CREATE INDEX idx_chapters_manga ON chapters(manga_id);
+CREATE INDEX idx_reading_progress_updated ON reading_progress(updated_at);
+CREATE INDEX idx_anilist_sync_status ON anilist_sync(status);
+
+ALTER TABLE download_jobs RENAME COLUMN anilist_id TO manga_id;
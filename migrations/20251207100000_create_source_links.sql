@@ -0,0 +1,13 @@
+-- Which library series a scraper's series page belongs to, so downloads from it don't
+-- need the series spelled out every time
+CREATE TABLE source_links (
+    source TEXT NOT NULL,
+    manga_url TEXT NOT NULL,
+    manga_id INTEGER NOT NULL REFERENCES manga(id) ON DELETE CASCADE,
+    -- Match score when linked automatically, NULL when the user chose the series
+    confidence REAL,
+    linked_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (source, manga_url)
+);
+
+CREATE INDEX idx_source_links_manga ON source_links(manga_id);
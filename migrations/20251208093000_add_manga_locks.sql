@@ -0,0 +1,4 @@
+-- Fields edited by hand; AniList refreshes leave them alone
+ALTER TABLE manga ADD COLUMN title_locked BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE manga ADD COLUMN author_locked BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE manga ADD COLUMN description_locked BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Page the chapter was downloaded from; NULL for uploads
+ALTER TABLE chapters ADD COLUMN source_url TEXT;
@@ -0,0 +1,4 @@
+-- Chapter details an upload's ComicInfo.xml can carry
+ALTER TABLE chapters ADD COLUMN volume INTEGER;
+ALTER TABLE chapters ADD COLUMN language TEXT;
+ALTER TABLE chapters ADD COLUMN reading_direction TEXT CHECK (reading_direction IN ('ltr', 'rtl'));
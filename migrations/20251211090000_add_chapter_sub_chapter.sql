@@ -0,0 +1,58 @@
+-- "Chapter 12.10" is a tenth part and "Chapter 12.1" a first, but both are 12.1 as a
+-- number. The digits after the point are kept in `sub_chapter` (0 for whole chapters) and
+-- become part of what makes a chapter unique. Existing rows take them from their number.
+--
+-- Renamed aside before dropping for the same reason as in add_series_id.
+ALTER TABLE chapters RENAME TO chapters_old;
+ALTER TABLE reading_progress RENAME TO reading_progress_old;
+
+CREATE TABLE chapters (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    manga_id INTEGER NOT NULL REFERENCES manga(id) ON DELETE CASCADE,
+    chapter_number REAL NOT NULL,
+    sub_chapter INTEGER NOT NULL DEFAULT 0,
+    title TEXT,
+    page_count INTEGER NOT NULL,
+    storage_path TEXT NOT NULL,
+    added_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    source_url TEXT,
+    volume INTEGER,
+    language TEXT,
+    reading_direction TEXT CHECK (reading_direction IN ('ltr', 'rtl')),
+    UNIQUE(manga_id, chapter_number, sub_chapter)
+);
+
+INSERT INTO chapters (id, manga_id, chapter_number, sub_chapter, title, page_count, storage_path,
+                      added_at, source_url, volume, language, reading_direction)
+SELECT id, manga_id, chapter_number,
+       CASE WHEN chapter_number = CAST(chapter_number AS INTEGER) THEN 0
+            ELSE CAST(substr(CAST(chapter_number AS TEXT),
+                             instr(CAST(chapter_number AS TEXT), '.') + 1) AS INTEGER)
+       END,
+       title, page_count, storage_path, added_at, source_url, volume, language, reading_direction
+FROM chapters_old;
+
+CREATE TABLE reading_progress (
+    manga_id INTEGER NOT NULL REFERENCES manga(id) ON DELETE CASCADE,
+    chapter_id INTEGER NOT NULL REFERENCES chapters(id) ON DELETE CASCADE,
+    page INTEGER NOT NULL DEFAULT 1,
+    completed BOOLEAN NOT NULL DEFAULT 0,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (manga_id, chapter_id)
+);
+
+INSERT INTO reading_progress (manga_id, chapter_id, page, completed, updated_at)
+SELECT manga_id, chapter_id, page, completed, updated_at
+FROM reading_progress_old;
+
+DROP TABLE reading_progress_old;
+DROP TABLE chapters_old;
+
+CREATE INDEX idx_chapters_manga ON chapters(manga_id);
+CREATE INDEX idx_reading_progress_updated ON reading_progress(updated_at);
+
+ALTER TABLE download_jobs ADD COLUMN sub_chapter INTEGER NOT NULL DEFAULT 0;
+UPDATE download_jobs
+SET sub_chapter = CAST(substr(CAST(chapter_number AS TEXT),
+                              instr(CAST(chapter_number AS TEXT), '.') + 1) AS INTEGER)
+WHERE chapter_number != CAST(chapter_number AS INTEGER);
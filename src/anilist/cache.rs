@@ -0,0 +1,384 @@
+use std::env;
+use std::time::Duration;
+
+use bincode::config::standard;
+use bincode::{Decode, Encode, decode_from_slice, encode_to_vec};
+use chrono::Utc;
+use sqlx::{Pool, Sqlite};
+
+use super::{AniListClient, MangaMetadata, save_details};
+use crate::AppState;
+use crate::storage::kv::KVStore;
+
+const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+// How often the refresh task looks for manga whose metadata has expired
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Gap between two refreshes, so a large library stays well under AniList's rate limit
+const REFRESH_SPACING: Duration = Duration::from_secs(2);
+
+#[derive(Encode, Decode)]
+struct CachedMetadata {
+    /// Unix timestamp of the AniList response.
+    fetched_at: i64,
+    metadata: MangaMetadata,
+}
+
+/// AniList metadata kept in the KV store, so ingest doesn't hit AniList for every chapter
+/// and keeps working from the last known copy while it's down.
+#[derive(Clone)]
+pub struct MetadataCache {
+    kv: KVStore,
+    anilist: AniListClient,
+    ttl: Duration,
+}
+
+impl MetadataCache {
+    pub fn new(kv: KVStore, anilist: AniListClient, ttl: Duration) -> Self {
+        Self { kv, anilist, ttl }
+    }
+
+    /// The same cache, fetching through `anilist` from now on.
+    pub fn with_client(&self, anilist: AniListClient) -> Self {
+        Self {
+            anilist,
+            ..self.clone()
+        }
+    }
+
+    /// `METADATA_TTL_HOURS`, a week when unset.
+    pub fn ttl_from_env() -> Duration {
+        env::var("METADATA_TTL_HOURS")
+            .ok()
+            .and_then(|h| h.parse::<u64>().ok())
+            .map_or(DEFAULT_TTL, |h| {
+                Duration::from_secs(h.saturating_mul(60 * 60))
+            })
+    }
+
+    /// The cached copy while it's fresh, otherwise a new one from AniList.
+    /// Falls back to an expired copy when AniList can't be reached.
+    pub async fn get(&self, anilist_id: i64) -> anyhow::Result<MangaMetadata> {
+        let cached = self.cached(anilist_id).await;
+        if let Some(cached) = &cached
+            && !self.is_expired(cached)
+        {
+            return Ok(cached.metadata.clone());
+        }
+
+        match self.refresh(anilist_id).await {
+            Ok(metadata) => Ok(metadata),
+            Err(e) => match cached {
+                Some(cached) => {
+                    eprintln!("Using cached metadata for {anilist_id}, AniList failed: {e:?}");
+                    Ok(cached.metadata)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Fetches from AniList regardless of the cache and stores the result.
+    pub async fn refresh(&self, anilist_id: i64) -> anyhow::Result<MangaMetadata> {
+        let metadata = self.anilist.manga_metadata(anilist_id).await?;
+        let cached = CachedMetadata {
+            fetched_at: Utc::now().timestamp(),
+            metadata: metadata.clone(),
+        };
+        self.kv
+            .put(key(anilist_id), encode_to_vec(&cached, standard())?)
+            .await;
+        Ok(metadata)
+    }
+
+    pub async fn is_stale(&self, anilist_id: i64) -> bool {
+        self.cached(anilist_id)
+            .await
+            .is_none_or(|cached| self.is_expired(&cached))
+    }
+
+    async fn cached(&self, anilist_id: i64) -> Option<CachedMetadata> {
+        let bytes = self.kv.get(key(anilist_id).as_bytes()).await?;
+        decode_from_slice(&bytes, standard()).ok().map(|(c, _)| c)
+    }
+
+    fn is_expired(&self, cached: &CachedMetadata) -> bool {
+        Utc::now().timestamp() - cached.fetched_at >= self.ttl.as_secs() as i64
+    }
+}
+
+fn key(anilist_id: i64) -> String {
+    format!("anilist:media:{anilist_id}")
+}
+
+/// Re-fetches metadata for every linked manga whose cached copy has expired and writes it
+/// to its `manga` row, leaving fields edited by hand alone. Returns how many rows were refreshed.
+pub async fn refresh_stale_manga(
+    pool: &Pool<Sqlite>,
+    state: &AppState,
+    spacing: Duration,
+) -> anyhow::Result<usize> {
+    let cache = &state.metadata;
+    let linked = sqlx::query!(
+        r#"SELECT id as "id!", anilist_id as "anilist_id!" FROM manga WHERE anilist_id IS NOT NULL"#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut refreshed = 0;
+    for manga in linked {
+        let anilist_id = manga.anilist_id;
+        if !cache.is_stale(anilist_id).await {
+            continue;
+        }
+
+        // One series that can't be saved shouldn't hold up the rest
+        match cache.refresh(anilist_id).await {
+            Ok(metadata) => match apply_metadata(pool, state, manga.id, &metadata).await {
+                Ok(()) => refreshed += 1,
+                Err(e) => eprintln!("Could not save metadata for manga {}: {e:?}", manga.id),
+            },
+            Err(e) => eprintln!("Could not refresh metadata for {anilist_id}: {e:?}"),
+        }
+        tokio::time::sleep(spacing).await;
+    }
+
+    Ok(refreshed)
+}
+
+async fn apply_metadata(
+    pool: &Pool<Sqlite>,
+    state: &AppState,
+    manga_id: i64,
+    metadata: &MangaMetadata,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE manga
+        SET title = CASE WHEN title_locked THEN title ELSE ? END,
+            author = CASE WHEN author_locked THEN author ELSE COALESCE(?, author) END,
+            description = CASE WHEN description_locked THEN description
+                               ELSE COALESCE(?, description) END
+        WHERE id = ?
+        "#,
+        metadata.title,
+        metadata.author,
+        metadata.description,
+        manga_id
+    )
+    .execute(pool)
+    .await?;
+    save_details(pool, &state.fetcher, &state.image_dir, manga_id, metadata).await
+}
+
+/// Spawns the task that keeps `manga` rows in step with AniList.
+pub fn start_refresh(pool: Pool<Sqlite>, state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = refresh_stale_manga(&pool, &state, REFRESH_SPACING).await {
+                eprintln!("Metadata refresh failed: {e:?}");
+            }
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrrrr::serve_fixtures;
+    use crate::db;
+    use axum::http::StatusCode;
+    use axum::{Router, routing::post};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, OnceLock};
+    use tempfile::TempDir;
+
+    struct Stub {
+        endpoint: String,
+        hits: Arc<AtomicUsize>,
+        down: Arc<AtomicBool>,
+    }
+
+    // Answers every Media query with the One Piece fixture until `down` is set.
+    // Artwork URLs point back at the stub, which has none, so tests never leave the machine.
+    async fn stub() -> Stub {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let down = Arc::new(AtomicBool::new(false));
+        let base = Arc::new(OnceLock::<String>::new());
+        let (counter, is_down, own_base) = (hits.clone(), down.clone(), base.clone());
+        let router = Router::new().route(
+            "/",
+            post(move || {
+                let (counter, is_down, own_base) =
+                    (counter.clone(), is_down.clone(), own_base.clone());
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    if is_down.load(Ordering::SeqCst) {
+                        (StatusCode::SERVICE_UNAVAILABLE, "down".to_string())
+                    } else {
+                        let media = include_str!("fixtures/media.json")
+                            .replace("https://s4.anilist.co", own_base.get().unwrap());
+                        (StatusCode::OK, media)
+                    }
+                }
+            }),
+        );
+        let endpoint = serve_fixtures(router).await;
+        base.set(endpoint.clone()).unwrap();
+        Stub {
+            endpoint,
+            hits,
+            down,
+        }
+    }
+
+    fn cache(stub: &Stub, ttl: Duration) -> (MetadataCache, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let kv = KVStore::new(dir.path().to_path_buf());
+        let anilist = AniListClient::new(&stub.endpoint);
+        (MetadataCache::new(kv, anilist, ttl), dir)
+    }
+
+    #[tokio::test]
+    async fn test_fresh_entries_are_served_from_cache() {
+        let stub = stub().await;
+        let (cache, _dir) = cache(&stub, DEFAULT_TTL);
+
+        let first = cache.get(30013).await.unwrap();
+        let second = cache.get(30013).await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first.title, "One Piece");
+        assert_eq!(first.author.as_deref(), Some("Eiichiro Oda"));
+        assert_eq!(stub.hits.load(Ordering::SeqCst), 1);
+        assert!(!cache.is_stale(30013).await);
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_refetched() {
+        let stub = stub().await;
+        let (cache, _dir) = cache(&stub, Duration::ZERO);
+
+        cache.get(30013).await.unwrap();
+        cache.get(30013).await.unwrap();
+
+        assert_eq!(stub.hits.load(Ordering::SeqCst), 2);
+        assert!(cache.is_stale(30013).await);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_expired_copy_when_down() {
+        let stub = stub().await;
+        let (cache, _dir) = cache(&stub, Duration::ZERO);
+        cache.get(30013).await.unwrap();
+
+        stub.down.store(true, Ordering::SeqCst);
+
+        assert_eq!(cache.get(30013).await.unwrap().title, "One Piece");
+        assert!(cache.get(1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_stale_manga() {
+        let stub = stub().await;
+        let dirs = [TempDir::new().unwrap(), TempDir::new().unwrap()];
+        let state = AppState::new(dirs[0].path().to_path_buf(), dirs[1].path().to_path_buf())
+            .with_anilist(AniListClient::new(&stub.endpoint));
+        let pool = db::test_pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO manga (id, anilist_id, title, author, storage_path) VALUES
+                (1, 30013, 'Old Title', 'Old Author', 'data/manga/1'),
+                (2, 105778, 'Chainsaw Man', NULL, 'data/manga/2'),
+                (3, NULL, 'Doujin', NULL, 'data/manga/3');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        state.metadata.get(105778).await.unwrap();
+
+        let refreshed = refresh_stale_manga(&pool, &state, Duration::ZERO)
+            .await
+            .unwrap();
+
+        // 105778 was cached moments ago and the doujin isn't linked, so only 30013 is fetched
+        assert_eq!(refreshed, 1);
+        let manga = sqlx::query!(
+            "SELECT title, author, genres, tags, start_year FROM manga WHERE anilist_id = 30013"
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(manga.title, "One Piece");
+        assert_eq!(manga.author.as_deref(), Some("Eiichiro Oda"));
+        assert_eq!(manga.genres, r#"["Action","Adventure","Comedy"]"#);
+        // Spoiler tags are left out
+        assert_eq!(manga.tags, r#"["Pirates"]"#);
+        assert_eq!(manga.start_year, Some(1997));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_carries_on_past_failures() {
+        let stub = stub().await;
+        let dirs = [TempDir::new().unwrap(), TempDir::new().unwrap()];
+        let state = AppState::new(dirs[0].path().to_path_buf(), dirs[1].path().to_path_buf())
+            .with_anilist(AniListClient::new(&stub.endpoint));
+        let pool = db::test_pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO manga (id, anilist_id, title, storage_path) VALUES
+                (1, 1, 'Stuck', 'data/manga/1'),
+                (2, 30013, 'Old Title', 'data/manga/2');
+            CREATE TRIGGER stuck BEFORE UPDATE ON manga WHEN OLD.id = 1
+            BEGIN SELECT RAISE(ABORT, 'stuck'); END;
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let refreshed = refresh_stale_manga(&pool, &state, Duration::ZERO)
+            .await
+            .unwrap();
+
+        assert_eq!(refreshed, 1);
+        let title: String = sqlx::query_scalar("SELECT title FROM manga WHERE id = 2")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(title, "One Piece");
+    }
+
+    #[tokio::test]
+    async fn test_refresh_keeps_locked_fields() {
+        let stub = stub().await;
+        let dirs = [TempDir::new().unwrap(), TempDir::new().unwrap()];
+        let state = AppState::new(dirs[0].path().to_path_buf(), dirs[1].path().to_path_buf())
+            .with_anilist(AniListClient::new(&stub.endpoint));
+        let pool = db::test_pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO manga (id, anilist_id, title, author, storage_path, title_locked)
+            VALUES (1, 30013, 'ワンピース', 'Old Author', 'data/manga/1', 1);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        refresh_stale_manga(&pool, &state, Duration::ZERO)
+            .await
+            .unwrap();
+
+        let manga = sqlx::query!("SELECT title, author, start_year FROM manga WHERE id = 1")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(manga.title, "ワンピース");
+        assert_eq!(manga.author.as_deref(), Some("Eiichiro Oda"));
+        assert_eq!(manga.start_year, Some(1997));
+    }
+}
@@ -0,0 +1,229 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+use reqwest::header::HeaderMap;
+use sqlx::types::Json;
+use sqlx::{Pool, Sqlite};
+use tokio::fs;
+use url::Url;
+
+use super::MangaMetadata;
+use crate::fetcher::Fetcher;
+
+/// Writes the AniList details beyond title, author and description onto an existing manga
+/// row, keeping local copies of its cover and banner so the library renders offline.
+pub async fn save_details(
+    pool: &Pool<Sqlite>,
+    fetcher: &Fetcher,
+    image_dir: &Path,
+    manga_id: i64,
+    metadata: &MangaMetadata,
+) -> anyhow::Result<()> {
+    let current = sqlx::query!(
+        r#"
+        SELECT storage_path, cover_url, cover_path, banner_url, banner_path
+        FROM manga
+        WHERE id = ?
+        "#,
+        manga_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| anyhow!("Manga not found"))?;
+
+    let (cover_url, cover_path) = artwork(
+        fetcher,
+        image_dir,
+        &current.storage_path,
+        "cover",
+        metadata.cover_url.as_deref(),
+        (current.cover_url, current.cover_path),
+    )
+    .await;
+    let (banner_url, banner_path) = artwork(
+        fetcher,
+        image_dir,
+        &current.storage_path,
+        "banner",
+        metadata.banner_url.as_deref(),
+        (current.banner_url, current.banner_path),
+    )
+    .await;
+
+    let synonyms = Json(&metadata.synonyms);
+    let genres = Json(&metadata.genres);
+    let tags = Json(&metadata.tags);
+    sqlx::query!(
+        r#"
+        UPDATE manga
+        SET native_title = ?, synonyms = ?, genres = ?, tags = ?, status = ?,
+            total_chapters = ?, total_volumes = ?, start_year = ?,
+            cover_url = ?, cover_path = ?, banner_url = ?, banner_path = ?
+        WHERE id = ?
+        "#,
+        metadata.native_title,
+        synonyms,
+        genres,
+        tags,
+        metadata.status,
+        metadata.total_chapters,
+        metadata.total_volumes,
+        metadata.start_year,
+        cover_url,
+        cover_path,
+        banner_url,
+        banner_path,
+        manga_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// The URL and storage path to record for one image, downloading it when the URL changed
+// or the file went missing. A failed download keeps whatever was stored before.
+async fn artwork(
+    fetcher: &Fetcher,
+    image_dir: &Path,
+    storage_path: &str,
+    name: &str,
+    url: Option<&str>,
+    stored: (Option<String>, Option<String>),
+) -> (Option<String>, Option<String>) {
+    let Some(url) = url else {
+        return stored;
+    };
+    if stored.0.as_deref() == Some(url)
+        && let Some(path) = &stored.1
+        && image_dir.join(path).is_file()
+    {
+        return stored;
+    }
+
+    match download(fetcher, image_dir, storage_path, name, url).await {
+        Ok(path) => {
+            if let Some(old) = &stored.1
+                && *old != path
+            {
+                let _ = fs::remove_file(image_dir.join(old)).await;
+            }
+            (Some(url.to_string()), Some(path))
+        }
+        Err(e) => {
+            eprintln!("Could not download {name} from {url}: {e:?}");
+            stored
+        }
+    }
+}
+
+async fn download(
+    fetcher: &Fetcher,
+    image_dir: &Path,
+    storage_path: &str,
+    name: &str,
+    url: &str,
+) -> anyhow::Result<String> {
+    let url = Url::parse(url)?;
+    let extension = Path::new(url.path())
+        .extension()
+        .and_then(|e| e.to_str())
+        .filter(|e| matches!(*e, "jpg" | "jpeg" | "png" | "gif" | "webp"))
+        .unwrap_or("jpg")
+        .to_string();
+
+    let bytes = fetcher.get(url, &HeaderMap::new()).await?.bytes().await?;
+    if bytes.is_empty() {
+        return Err(anyhow!("Empty image"));
+    }
+
+    let path = format!("{storage_path}/{name}.{extension}");
+    let full_path = image_dir.join(&path);
+    if let Some(parent) = full_path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let partial = full_path.with_extension(format!("{extension}.part"));
+    fs::write(&partial, &bytes).await?;
+    fs::rename(&partial, &full_path).await?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrrrr::serve_fixtures;
+    use crate::db;
+    use axum::{Router, routing::get};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::TempDir;
+
+    fn metadata(base: &str) -> MangaMetadata {
+        MangaMetadata {
+            title: "One Piece".to_string(),
+            author: Some("Eiichiro Oda".to_string()),
+            description: None,
+            native_title: Some("ONE PIECE".to_string()),
+            synonyms: vec!["원피스".to_string()],
+            genres: vec!["Action".to_string(), "Adventure".to_string()],
+            tags: vec!["Pirates".to_string()],
+            status: Some("RELEASING".to_string()),
+            total_chapters: None,
+            total_volumes: None,
+            start_year: Some(1997),
+            cover_url: Some(format!("{base}/cover/bx30013.png")),
+            banner_url: Some(format!("{base}/missing.jpg")),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_details() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let router = Router::new().route(
+            "/cover/bx30013.png",
+            get(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async { "png bytes" }
+            }),
+        );
+        let base = serve_fixtures(router).await;
+        let image_dir = TempDir::new().unwrap();
+        let pool = db::test_pool().await;
+        sqlx::query(
+            "INSERT INTO manga (id, anilist_id, title, storage_path) VALUES (1, 30013, 'One Piece', 'data/manga/30013')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let fetcher = Fetcher::default();
+
+        for _ in 0..2 {
+            save_details(&pool, &fetcher, image_dir.path(), 1, &metadata(&base))
+                .await
+                .unwrap();
+        }
+
+        // Downloaded once, then reused while the URL stays the same
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        let cover = image_dir.path().join("data/manga/30013/cover.png");
+        assert_eq!(std::fs::read_to_string(cover).unwrap(), "png bytes");
+
+        let row = sqlx::query!(
+            "SELECT genres, start_year, cover_path, banner_url, banner_path FROM manga"
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(row.genres, r#"["Action","Adventure"]"#);
+        assert_eq!(row.start_year, Some(1997));
+        assert_eq!(
+            row.cover_path.as_deref(),
+            Some("data/manga/30013/cover.png")
+        );
+        // The banner 404s, so nothing is recorded for it
+        assert_eq!(row.banner_url, None);
+        assert_eq!(row.banner_path, None);
+    }
+}
@@ -0,0 +1,538 @@
+use bincode::{Decode, Encode};
+//use rust_anilist::Client;
+use reqwest::StatusCode;
+use reqwest::header::RETRY_AFTER;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod cache;
+mod details;
+mod search;
+
+pub use cache::{MetadataCache, refresh_stale_manga, start_refresh};
+pub use details::save_details;
+pub use search::{CONFIDENT_MATCH, MatchCandidate, confident_match};
+
+pub const ANILIST_URL: &str = "https://graphql.anilist.co";
+
+// A rate-limited request is retried this many times when AniList asks for a short enough wait
+const RATE_LIMIT_RETRIES: u32 = 2;
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// The GraphQL endpoint, `ANILIST_URL` when set.
+pub fn anilist_url() -> String {
+    env::var("ANILIST_URL").unwrap_or_else(|_| ANILIST_URL.into())
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct MangaMetadata {
+    pub title: String,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub native_title: Option<String>,
+    pub synonyms: Vec<String>,
+    pub genres: Vec<String>,
+    /// Tag names, leaving out spoilers.
+    pub tags: Vec<String>,
+    /// AniList's publication status, e.g. RELEASING or FINISHED.
+    pub status: Option<String>,
+    pub total_chapters: Option<i64>,
+    pub total_volumes: Option<i64>,
+    pub start_year: Option<i64>,
+    pub cover_url: Option<String>,
+    pub banner_url: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum AniListError {
+    NotFound,
+    /// Still limited after the retries; `retry_after` is AniList's last requested wait.
+    RateLimited {
+        retry_after: Option<Duration>,
+    },
+    /// The `errors` of a GraphQL response, e.g. an invalid token.
+    GraphQl(Vec<String>),
+    /// A non-success status without a GraphQL body explaining it.
+    Status(StatusCode),
+    Http(reqwest::Error),
+}
+
+impl fmt::Display for AniListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AniListError::NotFound => write!(f, "Not found on AniList"),
+            AniListError::RateLimited {
+                retry_after: Some(wait),
+            } => write!(f, "AniList rate limit hit, retry in {}s", wait.as_secs()),
+            AniListError::RateLimited { retry_after: None } => {
+                write!(f, "AniList rate limit hit")
+            }
+            AniListError::GraphQl(messages) => {
+                write!(f, "AniList API returned error: {}", messages.join("; "))
+            }
+            AniListError::Status(status) => write!(f, "AniList API returned error: {status}"),
+            AniListError::Http(e) => write!(f, "Could not reach AniList: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for AniListError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AniListError::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for AniListError {
+    fn from(e: reqwest::Error) -> Self {
+        AniListError::Http(e)
+    }
+}
+
+/// GraphQL access to AniList. Cheap to clone; clones share one connection pool.
+#[derive(Clone)]
+pub struct AniListClient {
+    client: reqwest::Client,
+    endpoint: Arc<str>,
+    token: Option<Arc<str>>,
+}
+
+impl AniListClient {
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.into(),
+            token: None,
+        }
+    }
+
+    /// The same client, authenticated as the owner of `token`.
+    pub fn with_token(&self, token: &str) -> Self {
+        Self {
+            token: Some(token.into()),
+            ..self.clone()
+        }
+    }
+
+    /// Runs a query or mutation, waiting out short rate limits.
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> Result<T, AniListError> {
+        let mut attempt = 0;
+        loop {
+            match self.send(query, &variables).await {
+                Err(AniListError::RateLimited {
+                    retry_after: Some(wait),
+                }) if attempt < RATE_LIMIT_RETRIES && wait <= MAX_RATE_LIMIT_WAIT => {
+                    attempt += 1;
+                    tokio::time::sleep(wait).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: &serde_json::Value,
+    ) -> Result<T, AniListError> {
+        let mut request = self.client.post(&*self.endpoint).json(&AniListQuery {
+            query: query.to_string(),
+            variables: variables.clone(),
+        });
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok()?.trim().parse().ok())
+                .map(Duration::from_secs);
+            return Err(AniListError::RateLimited { retry_after });
+        }
+
+        // AniList reports most failures, auth included, in `errors` alongside a 4xx
+        let body: GraphQlResponse<T> = match response.json().await {
+            Ok(body) => body,
+            Err(_) if status == StatusCode::NOT_FOUND => return Err(AniListError::NotFound),
+            Err(_) if !status.is_success() => return Err(AniListError::Status(status)),
+            Err(e) => return Err(e.into()),
+        };
+
+        if !body.errors.is_empty() {
+            if status == StatusCode::NOT_FOUND || body.errors.iter().any(|e| e.status == Some(404))
+            {
+                return Err(AniListError::NotFound);
+            }
+            return Err(AniListError::GraphQl(
+                body.errors.into_iter().map(|e| e.message).collect(),
+            ));
+        }
+        body.data.ok_or(AniListError::Status(status))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct AniListQuery {
+    query: String,
+    variables: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlError {
+    message: String,
+    status: Option<u16>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AniListData {
+    #[serde(rename = "Media")]
+    media: AniListMedia,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AniListMedia {
+    title: AniListTitle,
+    description: Option<String>,
+    staff: Option<AniListStaff>,
+    #[serde(default)]
+    synonyms: Vec<String>,
+    #[serde(default)]
+    genres: Vec<String>,
+    #[serde(default)]
+    tags: Vec<AniListTag>,
+    status: Option<String>,
+    chapters: Option<i64>,
+    volumes: Option<i64>,
+    start_date: Option<AniListDate>,
+    cover_image: Option<AniListCover>,
+    banner_image: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AniListTitle {
+    romaji: Option<String>,
+    english: Option<String>,
+    native: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AniListTag {
+    name: String,
+    #[serde(default)]
+    is_general_spoiler: bool,
+    #[serde(default)]
+    is_media_spoiler: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct AniListDate {
+    year: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AniListCover {
+    extra_large: Option<String>,
+    large: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AniListStaff {
+    edges: Vec<AniListStaffEdge>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AniListStaffEdge {
+    role: String,
+    node: AniListStaffNode,
+}
+
+#[derive(Debug, Deserialize)]
+struct AniListStaffNode {
+    name: AniListName,
+}
+
+#[derive(Debug, Deserialize)]
+struct AniListName {
+    full: String,
+}
+
+/*
+pub async fn fetch_manga_metadata(
+    anilist_id: i64,
+) -> anyhow::Result<(String, Option<String>, String)> {
+    let client = Client::with_token("");
+    let manga = client.get_manga(anilist_id).await?;
+    let mut title = manga.title.english();
+    if title.is_empty() {
+        title = manga.title.romaji();
+        if title.is_empty() {
+            title = "No title found"
+        }
+    }
+    let author = manga.staff.and_then(|staff| {
+        staff
+            .iter()
+            .find(|edge| {
+                let d = edge.description.as_deref().unwrap_or_default();
+                d.contains("Story") || d.contains("Original Creator")
+            })
+            .map(|edge| edge.name.full().clone())
+    });
+    return Ok((title.to_string(), author, manga.description));
+}
+*/
+
+impl AniListClient {
+    pub async fn manga_metadata(&self, anilist_id: i64) -> Result<MangaMetadata, AniListError> {
+        let query = r#"
+            query ($id: Int) {
+                Media(id: $id, type: MANGA) {
+                    title {
+                        romaji
+                        english
+                        native
+                    }
+                    synonyms
+                    description(asHtml: false)
+                    genres
+                    tags {
+                        name
+                        isGeneralSpoiler
+                        isMediaSpoiler
+                    }
+                    status
+                    chapters
+                    volumes
+                    startDate {
+                        year
+                    }
+                    coverImage {
+                        extraLarge
+                        large
+                    }
+                    bannerImage
+                    staff(perPage: 25) {
+                        edges {
+                            role
+                            node {
+                                name {
+                                    full
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        "#;
+
+        let variables = serde_json::json!({
+            "id": anilist_id
+        });
+
+        let data: AniListData = self.query(query, variables).await?;
+        let media = data.media;
+
+        let native_title = media.title.native;
+        let title = media
+            .title
+            .english
+            .or(media.title.romaji)
+            .or_else(|| native_title.clone())
+            .ok_or(AniListError::NotFound)?;
+
+        let author = media.staff.and_then(|staff| {
+            staff
+                .edges
+                .iter()
+                .find(|edge| edge.role.contains("Story") || edge.role.contains("Original Creator"))
+                .map(|edge| edge.node.name.full.clone())
+        });
+
+        Ok(MangaMetadata {
+            title,
+            author,
+            description: media.description,
+            native_title,
+            synonyms: media.synonyms,
+            genres: media.genres,
+            tags: media
+                .tags
+                .into_iter()
+                .filter(|t| !t.is_general_spoiler && !t.is_media_spoiler)
+                .map(|t| t.name)
+                .collect(),
+            status: media.status,
+            total_chapters: media.chapters,
+            total_volumes: media.volumes,
+            start_year: media.start_date.and_then(|d| d.year),
+            cover_url: media.cover_image.and_then(|c| c.extra_large.or(c.large)),
+            banner_url: media.banner_image,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrrrr::serve_fixtures;
+    use axum::http::HeaderMap;
+    use axum::response::IntoResponse;
+    use axum::{Router, routing::post};
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Serves `responses` in order, repeating the last one, and counts requests
+    async fn stub(
+        responses: Vec<(StatusCode, Option<&'static str>, &'static str)>,
+    ) -> (AniListClient, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let router = Router::new().route(
+            "/",
+            post(move || {
+                let hit = counter.fetch_add(1, Ordering::SeqCst);
+                let (status, retry_after, body) = responses[hit.min(responses.len() - 1)];
+                async move {
+                    let mut headers = HeaderMap::new();
+                    if let Some(wait) = retry_after {
+                        headers.insert(RETRY_AFTER, wait.parse().unwrap());
+                    }
+                    (status, headers, body).into_response()
+                }
+            }),
+        );
+        (AniListClient::new(&serve_fixtures(router).await), hits)
+    }
+
+    #[tokio::test]
+    async fn test_manga_metadata() {
+        let (client, _) = stub(vec![(
+            StatusCode::OK,
+            None,
+            include_str!("fixtures/media.json"),
+        )])
+        .await;
+
+        let metadata = client.manga_metadata(30013).await.unwrap();
+
+        assert_eq!(metadata.title, "One Piece");
+        assert_eq!(metadata.native_title.as_deref(), Some("ONE PIECE"));
+        assert_eq!(metadata.tags, vec!["Pirates"]);
+        assert_eq!(metadata.status.as_deref(), Some("RELEASING"));
+        assert_eq!(metadata.start_year, Some(1997));
+        assert!(metadata.cover_url.unwrap().ends_with("/large/bx30013.jpg"));
+    }
+
+    #[tokio::test]
+    async fn test_not_found() {
+        let (client, _) = stub(vec![(
+            StatusCode::NOT_FOUND,
+            None,
+            r#"{"data":{"Media":null},"errors":[{"message":"Not Found.","status":404}]}"#,
+        )])
+        .await;
+
+        let err = client.manga_metadata(1).await.unwrap_err();
+        assert!(matches!(err, AniListError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_graphql_errors() {
+        let (client, _) = stub(vec![(
+            StatusCode::BAD_REQUEST,
+            None,
+            r#"{"data":null,"errors":[{"message":"Invalid token","status":400}]}"#,
+        )])
+        .await;
+
+        let err = client.manga_metadata(1).await.unwrap_err();
+        assert!(matches!(&err, AniListError::GraphQl(m) if m == &["Invalid token"]));
+        assert_eq!(err.to_string(), "AniList API returned error: Invalid token");
+    }
+
+    #[tokio::test]
+    async fn test_waits_out_short_rate_limits() {
+        let (client, hits) = stub(vec![
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                Some("0"),
+                "Too Many Requests.",
+            ),
+            (StatusCode::OK, None, include_str!("fixtures/media.json")),
+        ])
+        .await;
+
+        assert!(client.manga_metadata(30013).await.is_ok());
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reports_long_rate_limits() {
+        let (client, hits) = stub(vec![(
+            StatusCode::TOO_MANY_REQUESTS,
+            Some("120"),
+            "Too Many Requests.",
+        )])
+        .await;
+
+        let err = client.manga_metadata(30013).await.unwrap_err();
+        assert!(matches!(
+            err,
+            AniListError::RateLimited { retry_after: Some(wait) } if wait == Duration::from_secs(120)
+        ));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_with_token_authenticates() {
+        let seen = Arc::new(Mutex::new(None));
+        let recorder = seen.clone();
+        let router = Router::new().route(
+            "/",
+            post(move |headers: HeaderMap| {
+                *recorder.lock().unwrap() = headers
+                    .get("authorization")
+                    .map(|v| v.to_str().unwrap().to_string());
+                async { r#"{"data":{"Viewer":{"id":1}}}"# }
+            }),
+        );
+        let client = AniListClient::new(&serve_fixtures(router).await);
+
+        let _: serde_json::Value = client
+            .query("query { Viewer { id } }", serde_json::json!({}))
+            .await
+            .unwrap();
+        assert_eq!(*seen.lock().unwrap(), None);
+
+        let _: serde_json::Value = client
+            .with_token("secret")
+            .query("query { Viewer { id } }", serde_json::json!({}))
+            .await
+            .unwrap();
+        assert_eq!(seen.lock().unwrap().as_deref(), Some("Bearer secret"));
+    }
+}
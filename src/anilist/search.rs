@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{AniListClient, AniListError};
+
+/// Candidates scoring at least this are considered the same series.
+pub const CONFIDENT_MATCH: f64 = 0.9;
+
+// Light novels share titles with their adaptations and are never what a scraper serves
+const SEARCH_QUERY: &str = r#"
+    query ($search: String) {
+        Page(perPage: 10) {
+            media(search: $search, type: MANGA, format_not: NOVEL) {
+                id
+                title {
+                    romaji
+                    english
+                    native
+                }
+                synonyms
+                format
+                startDate {
+                    year
+                }
+                coverImage {
+                    large
+                }
+            }
+        }
+    }
+"#;
+
+/// An AniList entry that might be the series behind a scraped title.
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchCandidate {
+    pub anilist_id: i64,
+    pub title: String,
+    pub native_title: Option<String>,
+    pub synonyms: Vec<String>,
+    pub format: Option<String>,
+    pub start_year: Option<i64>,
+    pub cover_url: Option<String>,
+    /// From 0 to 1, how closely the best of its titles and synonyms matches.
+    pub confidence: f64,
+}
+
+#[derive(Deserialize)]
+struct SearchData {
+    #[serde(rename = "Page")]
+    page: SearchPage,
+}
+
+#[derive(Deserialize)]
+struct SearchPage {
+    media: Vec<SearchMedia>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchMedia {
+    id: i64,
+    title: super::AniListTitle,
+    #[serde(default)]
+    synonyms: Vec<String>,
+    format: Option<String>,
+    start_date: Option<super::AniListDate>,
+    cover_image: Option<SearchCover>,
+}
+
+#[derive(Deserialize)]
+struct SearchCover {
+    large: Option<String>,
+}
+
+impl AniListClient {
+    /// AniList entries for `title`, most confident first.
+    pub async fn match_title(&self, title: &str) -> Result<Vec<MatchCandidate>, AniListError> {
+        let data: SearchData = self
+            .query(SEARCH_QUERY, serde_json::json!({ "search": title }))
+            .await?;
+
+        let mut candidates: Vec<MatchCandidate> = data
+            .page
+            .media
+            .into_iter()
+            .map(|media| {
+                let names = [
+                    &media.title.english,
+                    &media.title.romaji,
+                    &media.title.native,
+                ];
+                let confidence = names
+                    .into_iter()
+                    .flatten()
+                    .chain(&media.synonyms)
+                    .map(|name| similarity(title, name))
+                    .fold(0.0, f64::max);
+
+                MatchCandidate {
+                    anilist_id: media.id,
+                    title: media
+                        .title
+                        .english
+                        .or(media.title.romaji)
+                        .or_else(|| media.title.native.clone())
+                        .unwrap_or_else(|| media.id.to_string()),
+                    native_title: media.title.native,
+                    synonyms: media.synonyms,
+                    format: media.format,
+                    start_year: media.start_date.and_then(|d| d.year),
+                    cover_url: media.cover_image.and_then(|c| c.large),
+                    confidence,
+                }
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+        Ok(candidates)
+    }
+}
+
+/// The one candidate at or above `CONFIDENT_MATCH`, if exactly one is.
+pub fn confident_match(candidates: &[MatchCandidate]) -> Option<&MatchCandidate> {
+    let mut confident = candidates
+        .iter()
+        .filter(|c| c.confidence >= CONFIDENT_MATCH);
+    let first = confident.next()?;
+    confident.next().is_none().then_some(first)
+}
+
+// Sørensen–Dice over character pairs, ignoring case, punctuation and spacing, so
+// "One Punch-Man" and "One-Punch Man" are the same title
+fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (normalize(a), normalize(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+
+    let (a, b) = (bigrams(&a), bigrams(&b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let mut counts: HashMap<(char, char), usize> = HashMap::new();
+    for pair in &a {
+        *counts.entry(*pair).or_default() += 1;
+    }
+    let shared = b
+        .iter()
+        .filter(|pair| match counts.get_mut(pair) {
+            Some(n) if *n > 0 => {
+                *n -= 1;
+                true
+            }
+            _ => false,
+        })
+        .count();
+
+    2.0 * shared as f64 / (a.len() + b.len()) as f64
+}
+
+fn normalize(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn bigrams(s: &str) -> Vec<(char, char)> {
+    let chars: Vec<char> = s.chars().collect();
+    chars.windows(2).map(|w| (w[0], w[1])).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrrrr::serve_fixtures;
+    use axum::{Router, routing::post};
+
+    fn candidate(anilist_id: i64, confidence: f64) -> MatchCandidate {
+        MatchCandidate {
+            anilist_id,
+            title: anilist_id.to_string(),
+            native_title: None,
+            synonyms: Vec::new(),
+            format: None,
+            start_year: None,
+            cover_url: None,
+            confidence,
+        }
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("One Punch-Man", "One-Punch Man"), 1.0);
+        assert_eq!(similarity("Chainsaw Man", "CHAINSAW MAN"), 1.0);
+        assert!(similarity("Chainsaw Man", "Chainsaw Man: Buddy Stories") < CONFIDENT_MATCH);
+        assert!(similarity("One Piece", "Chainsaw Man") < 0.2);
+        assert_eq!(similarity("!!!", "One Piece"), 0.0);
+    }
+
+    #[test]
+    fn test_confident_match_needs_a_single_candidate() {
+        let single = [candidate(1, 1.0), candidate(2, 0.6)];
+        assert_eq!(confident_match(&single).unwrap().anilist_id, 1);
+
+        let ambiguous = [candidate(1, 1.0), candidate(2, 0.95)];
+        assert!(confident_match(&ambiguous).is_none());
+
+        assert!(confident_match(&[candidate(1, 0.8)]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_match_title() {
+        let router =
+            Router::new().route("/", post(|| async { include_str!("fixtures/search.json") }));
+        let client = AniListClient::new(&serve_fixtures(router).await);
+
+        // Matched on a synonym
+        let candidates = client.match_title("CSM").await.unwrap();
+
+        assert_eq!(candidates.len(), 3);
+        assert_eq!(candidates[0].anilist_id, 105778);
+        assert_eq!(candidates[0].confidence, 1.0);
+        assert_eq!(candidates[0].start_year, Some(2018));
+        assert!(candidates[1].confidence < 0.5);
+        assert_eq!(confident_match(&candidates).unwrap().anilist_id, 105778);
+        assert_eq!(candidates[2].title, "Chainsaw Man: Koushiki Guidebook");
+    }
+}
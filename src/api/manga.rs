@@ -1,83 +1,207 @@
 use anyhow::anyhow;
 use axum::{
     Extension, Json, Router,
-    extract::{DefaultBodyLimit, Multipart, Path, State},
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State, multipart::Field},
     http::StatusCode,
     response::{IntoResponse, Response},
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
 };
 use serde::{Deserialize, Serialize};
-use sqlx::{Pool, Sqlite, prelude::FromRow};
+use sqlx::{Pool, Sqlite, prelude::FromRow, types::Json as JsonColumn};
+use std::env;
+use tempfile::NamedTempFile;
 use tokio::fs::{self, File};
 use tokio::io::AsyncWriteExt;
 
+use crate::arrrrr::label::{chapter_dir, parse_chapter_number, sub_chapter_of};
+use crate::ingest::{self, ExtractLimits, ReadingDirection, is_image_file};
+use crate::jobs::publish_chapter;
+use crate::sync::{AniListSync, ListStatus};
 use crate::{AppState, anilist};
 
+const DEFAULT_UPLOAD_LIMIT: usize = 2 * 1024 * 1024 * 1024;
+
+// Uploads and their extracted pages wait here, under `image_dir` so the final move is a rename
+const UPLOADS_DIR: &str = ".uploads";
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", post(upload_manga))
-        .layer(DefaultBodyLimit::max(100 * 1024 * 1024))
+        .layer(DefaultBodyLimit::max(upload_limit()))
         .route("/", get(list_manga))
-        .route("/{anilist_id}", get(get_manga))
-        .route("/{anilist_id}", delete(delete_manga))
-        .route("/{anilist_id}/chapters", get(list_chapters))
+        .route("/series", post(create_series))
+        .route("/{manga_id}", get(get_manga))
+        .route("/{manga_id}", put(update_manga))
+        .route("/{manga_id}", delete(delete_manga))
+        .route(
+            "/{manga_id}/anilist",
+            put(link_anilist).delete(unlink_anilist),
+        )
+        .route("/{manga_id}/chapters", get(list_chapters))
+        .route(
+            "/anilist/{anilist_id}",
+            get(get_manga_by_anilist).delete(delete_manga_by_anilist),
+        )
+        .route("/{manga_id}/{artwork}", get(get_artwork))
+        .route(
+            "/chapters/{chapter_id}",
+            get(get_chapter).put(update_chapter).delete(delete_chapter),
+        )
         .route("/chapters/{chapter_id}/pages/{page_num}", get(get_page))
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Manga {
-    pub anilist_id: i64,
+    pub id: i64,
+    /// None for series AniList doesn't list, or that haven't been linked yet.
+    pub anilist_id: Option<i64>,
     pub title: String,
     pub author: Option<String>,
     pub description: Option<String>,
     pub storage_path: String,
     pub added_at: String,
     pub updated_at: String,
+    pub native_title: Option<String>,
+    pub synonyms: JsonColumn<Vec<String>>,
+    pub genres: JsonColumn<Vec<String>>,
+    pub tags: JsonColumn<Vec<String>>,
+    pub status: Option<String>,
+    pub total_chapters: Option<i64>,
+    pub total_volumes: Option<i64>,
+    pub start_year: Option<i64>,
+    pub cover_url: Option<String>,
+    /// Local copy, served from `/manga/{id}/cover`.
+    pub cover_path: Option<String>,
+    pub banner_url: Option<String>,
+    /// Local copy, served from `/manga/{id}/banner`.
+    pub banner_path: Option<String>,
+    /// Set once edited by hand, after which AniList no longer overwrites the field.
+    pub title_locked: bool,
+    pub author_locked: bool,
+    pub description_locked: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Chapter {
     pub id: i64,
-    pub anilist_id: i64,
+    pub manga_id: i64,
     pub chapter_number: f64,
+    /// Digits after the point as written, so 12.10 is 10 where 12.1 is 1; 0 for whole ones.
+    pub sub_chapter: i64,
     pub title: Option<String>,
     pub page_count: i64,
     pub storage_path: String,
     pub added_at: String,
+    /// Page it was downloaded from; None for uploads.
+    pub source_url: Option<String>,
+    pub volume: Option<i64>,
+    /// ISO code of the language it's in.
+    pub language: Option<String>,
+    pub reading_direction: Option<ReadingDirection>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChapterDetails {
+    #[serde(flatten)]
+    pub chapter: Chapter,
+    /// Bytes on disk across all of its pages.
+    pub size: u64,
+}
+
+/// Renumbers, retitles or moves a chapter to another series. An empty title clears it.
+#[derive(Debug, Deserialize)]
+pub struct UpdateChapterRequest {
+    pub chapter_number: Option<f64>,
+    /// Only needed where `chapter_number` drops it, as with a tenth part.
+    pub sub_chapter: Option<i64>,
+    pub title: Option<String>,
+    pub manga_id: Option<i64>,
+}
+
+/// `?list=reading` narrows the library to series on that AniList list, as last pulled.
+#[derive(Debug, Deserialize)]
+pub struct LibraryFilter {
+    pub list: Option<ListStatus>,
+}
+
+/// A series entered by hand, for anything AniList doesn't list.
+#[derive(Debug, Deserialize)]
+pub struct CreateSeriesRequest {
+    pub title: String,
+    pub author: Option<String>,
+    pub description: Option<String>,
 }
 
-#[derive(Debug, Deserialize, FromRow)]
+#[derive(Debug, Deserialize)]
+pub struct LinkAniListRequest {
+    pub anilist_id: i64,
+}
+
+/// Fields given are saved and locked. Fields in `unlock` follow AniList again from its next refresh.
+#[derive(Debug, Deserialize)]
 pub struct UpdateMangaRequest {
     pub title: Option<String>,
     pub author: Option<String>,
     pub description: Option<String>,
+    #[serde(default)]
+    pub unlock: Vec<MangaField>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MangaField {
+    Title,
+    Author,
+    Description,
 }
 
-// POST /manga - Upload CBZ with metadata
+// POST /manga - Upload a CBZ, CBR, CB7, CBT, PDF or EPUB, with fields overriding its ComicInfo.xml
 #[axum::debug_handler]
 pub async fn upload_manga(
     State(state): State<AppState>,
     Extension(pool): Extension<Pool<Sqlite>>,
     mut multipart: Multipart,
 ) -> Result<Json<Manga>, AppError> {
+    let uploads_dir = state.image_dir.join(UPLOADS_DIR);
+    let mut manga_id: Option<i64> = None;
     let mut anilist_id: Option<i64> = None;
-    let mut chapter_number: Option<f64> = None;
-    let mut cbz_data: Option<Vec<u8>> = None;
+    let mut chapter_number: Option<(f64, i64)> = None;
+    let mut title: Option<String> = None;
+    let mut volume: Option<i64> = None;
+    let mut reading_direction: Option<ReadingDirection> = None;
+    let mut upload: Option<NamedTempFile> = None;
 
     while let Some(field) = multipart.next_field().await? {
         let name = field.name().unwrap_or("").to_string();
 
         match name.as_str() {
+            "manga_id" => {
+                let text = field.text().await?;
+                manga_id = Some(text.parse()?);
+            }
             "anilist_id" => {
                 let text = field.text().await?;
                 anilist_id = Some(text.parse()?);
             }
             "chapter_number" => {
                 let text = field.text().await?;
-                chapter_number = Some(text.parse()?);
+                chapter_number = Some(parse_chapter_number(&text).ok_or_else(|| {
+                    anyhow!("chapter_number must be a chapter number such as 12 or 12.5")
+                })?);
+            }
+            "title" => {
+                title = Some(field.text().await?);
+            }
+            "volume" => {
+                let text = field.text().await?;
+                volume = Some(text.parse()?);
+            }
+            "reading_direction" => {
+                let text = field.text().await?;
+                reading_direction = Some(text.parse()?);
             }
             "file" => {
-                cbz_data = Some(field.bytes().await?.to_vec());
+                upload = Some(save_upload(field, &uploads_dir).await?);
             }
             _ => {
                 println!("Unknown field: {}", name);
@@ -85,103 +209,335 @@ pub async fn upload_manga(
         }
     }
 
-    let anilist_id = anilist_id.ok_or_else(|| anyhow!("anilist_id is required"))?;
-    let chapter_number = chapter_number.ok_or_else(|| anyhow!("chapter_number is required"))?;
-    let cbz_data = cbz_data.ok_or_else(|| anyhow!("file is required"))?;
+    let upload = upload.ok_or_else(|| anyhow!("file is required"))?;
 
-    let (title, author, description) = anilist::fetch_manga_metadata(anilist_id).await?;
+    // Pages are extracted next to the upload and swapped in once all of them are out,
+    // so a bad archive never creates a series or replaces a chapter that was already there
+    let staging = tempfile::Builder::new()
+        .prefix("chapter-")
+        .tempdir_in(&uploads_dir)?;
+    let (archive, dest) = (upload.path().to_path_buf(), staging.path().to_path_buf());
+    let extracted = tokio::task::spawn_blocking(move || {
+        ingest::extract_archive(&archive, &dest, &ExtractLimits::default())
+    })
+    .await??;
+    let info = extracted.comic_info.unwrap_or_default();
 
-    let manga_storage_path = format!("data/manga/{}", anilist_id);
-    let chapter_storage_path = format!("{}/chapter_{}", manga_storage_path, chapter_number);
+    let (chapter_number, sub_chapter) = chapter_number
+        .or(info.number)
+        .ok_or_else(|| anyhow!("chapter_number is required when ComicInfo.xml has no Number"))?;
+    let title = title.or(info.title).filter(|t| !t.trim().is_empty());
+    let volume = volume.or(info.volume);
+    let reading_direction = reading_direction.or(info.reading_direction);
 
-    let mut full_chapter_path = state.image_dir.clone();
-    full_chapter_path.push(&chapter_storage_path);
-    fs::create_dir_all(&full_chapter_path).await?;
+    let manga_id = match (manga_id, anilist_id, &info.series) {
+        (None, None, Some(series)) => series_named(&pool, series, info.writer.as_deref()).await?,
+        _ => resolve_series(&pool, &state, manga_id, anilist_id).await?,
+    };
+    let manga_storage_path =
+        sqlx::query_scalar!("SELECT storage_path FROM manga WHERE id = ?", manga_id)
+            .fetch_one(&pool)
+            .await?;
+    let chapter_storage_path = format!(
+        "{}/{}",
+        manga_storage_path,
+        chapter_dir(chapter_number, sub_chapter)
+    );
 
-    // Extract CBZ
-    let cursor = std::io::Cursor::new(cbz_data);
-    let mut archive = zip::ZipArchive::new(cursor)?;
+    publish_chapter(staging.path(), &state.image_dir.join(&chapter_storage_path)).await?;
+    let page_count = extracted.page_count as i64;
 
-    let mut page_count = 0;
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        let filename = file.name().to_string();
+    sqlx::query!(
+        "UPDATE manga SET updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        manga_id
+    )
+    .execute(&pool)
+    .await?;
 
-        // Skip directories and non-image files
-        if file.is_dir() || !is_image_file(&filename) {
-            continue;
-        }
+    // A replacement that doesn't say otherwise keeps the chapter's details
+    sqlx::query!(
+        r#"
+        INSERT INTO chapters (manga_id, chapter_number, sub_chapter, title, page_count,
+                              storage_path, volume, language, reading_direction)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(manga_id, chapter_number, sub_chapter) DO UPDATE SET
+            title = COALESCE(excluded.title, title),
+            page_count = excluded.page_count,
+            storage_path = excluded.storage_path,
+            volume = COALESCE(excluded.volume, volume),
+            language = COALESCE(excluded.language, language),
+            reading_direction = COALESCE(excluded.reading_direction, reading_direction),
+            source_url = NULL
+        "#,
+        manga_id,
+        chapter_number,
+        sub_chapter,
+        title,
+        page_count,
+        chapter_storage_path,
+        volume,
+        info.language,
+        reading_direction
+    )
+    .execute(&pool)
+    .await?;
+
+    let manga = find_manga(&pool, manga_id)
+        .await?
+        .ok_or_else(|| anyhow!("Manga not found"))?;
 
-        let mut output_path = full_chapter_path.clone();
-        output_path.push(&filename);
+    Ok(Json(manga))
+}
 
-        let mut output_file = File::create(output_path).await?;
-        let mut buffer = Vec::new();
-        std::io::Read::read_to_end(&mut file, &mut buffer)?;
-        output_file.write_all(&buffer).await?;
+// Streams an uploaded file to a temporary file under `dir`, removed again when dropped
+async fn save_upload(mut field: Field<'_>, dir: &std::path::Path) -> anyhow::Result<NamedTempFile> {
+    fs::create_dir_all(dir).await?;
+    let upload = tempfile::Builder::new()
+        .prefix("upload-")
+        .tempfile_in(dir)?;
 
-        page_count += 1;
+    let mut file = File::from_std(upload.reopen()?);
+    while let Some(chunk) = field.chunk().await? {
+        file.write_all(&chunk).await?;
     }
+    file.flush().await?;
 
-    if page_count == 0 {
-        return Err(anyhow!("No image files found in CBZ").into());
+    Ok(upload)
+}
+
+/// `MAX_UPLOAD_MB`, 2 GiB when unset or too large to count in bytes, so whole volumes fit.
+pub fn upload_limit() -> usize {
+    env::var("MAX_UPLOAD_MB")
+        .ok()
+        .and_then(|mb| mb.parse::<usize>().ok())
+        .and_then(|mb| mb.checked_mul(1024 * 1024))
+        .unwrap_or(DEFAULT_UPLOAD_LIMIT)
+}
+
+// POST /manga/series - Create a series from hand-entered metadata
+pub async fn create_series(
+    Extension(pool): Extension<Pool<Sqlite>>,
+    Json(req): Json<CreateSeriesRequest>,
+) -> Result<Json<Manga>, AppError> {
+    if req.title.trim().is_empty() {
+        return Err(anyhow!("title is required").into());
     }
 
-    let manga = sqlx::query_as!(
-        Manga,
+    let manga_id = insert_series(
+        &pool,
+        None,
+        req.title.trim(),
+        req.author.as_deref(),
+        req.description.as_deref(),
+    )
+    .await?;
+    let manga = find_manga(&pool, manga_id)
+        .await?
+        .ok_or_else(|| anyhow!("Manga not found"))?;
+
+    Ok(Json(manga))
+}
+
+// PUT /manga/:manga_id/anilist - Link a series to an AniList entry, or move the link to another
+pub async fn link_anilist(
+    State(state): State<AppState>,
+    Extension(pool): Extension<Pool<Sqlite>>,
+    Path(manga_id): Path<i64>,
+    Json(req): Json<LinkAniListRequest>,
+) -> Result<Json<Manga>, AppError> {
+    let current = find_manga(&pool, manga_id)
+        .await?
+        .ok_or_else(|| anyhow!("Manga not found"))?;
+
+    let linked_elsewhere = sqlx::query_scalar!(
+        r#"SELECT id as "id!" FROM manga WHERE anilist_id = ? AND id != ?"#,
+        req.anilist_id,
+        manga_id
+    )
+    .fetch_optional(&pool)
+    .await?;
+    if let Some(other) = linked_elsewhere {
+        return Err(anyhow!(
+            "AniList entry {} is already linked to series {other}",
+            req.anilist_id
+        )
+        .into());
+    }
+
+    let metadata = state.metadata.get(req.anilist_id).await?;
+
+    sqlx::query!(
         r#"
-        INSERT INTO manga (anilist_id, title, author, description, storage_path)
-        VALUES (?, ?, ?, ?, ?)
-        ON CONFLICT(anilist_id) DO UPDATE SET
-            title = COALESCE(excluded.title, manga.title),
-            author = COALESCE(excluded.author, manga.author),
-            description = COALESCE(excluded.description, manga.description),
+        UPDATE manga
+        SET anilist_id = ?,
+            title = CASE WHEN title_locked THEN title ELSE ? END,
+            author = CASE WHEN author_locked THEN author ELSE COALESCE(?, author) END,
+            description = CASE WHEN description_locked THEN description
+                               ELSE COALESCE(?, description) END,
             updated_at = CURRENT_TIMESTAMP
-        RETURNING anilist_id, title, author as "author?", description as "description?",
-                   storage_path,
-                  added_at as "added_at: String", updated_at as "updated_at: String"
+        WHERE id = ?
         "#,
-        anilist_id,
-        title,
-        author,
-        description,
-        manga_storage_path
+        req.anilist_id,
+        metadata.title,
+        metadata.author,
+        metadata.description,
+        manga_id
     )
-    .fetch_one(&pool)
+    .execute(&pool)
     .await?;
 
+    // Anything pushed before went to the old entry
+    if current.anilist_id != Some(req.anilist_id) {
+        sqlx::query!("DELETE FROM anilist_sync WHERE manga_id = ?", manga_id)
+            .execute(&pool)
+            .await?;
+    }
+
+    anilist::save_details(&pool, &state.fetcher, &state.image_dir, manga_id, &metadata).await?;
+
+    let manga = find_manga(&pool, manga_id)
+        .await?
+        .ok_or_else(|| anyhow!("Manga not found"))?;
+
+    Ok(Json(manga))
+}
+
+// DELETE /manga/:manga_id/anilist - Keep the series and its metadata but drop the AniList link
+pub async fn unlink_anilist(
+    Extension(pool): Extension<Pool<Sqlite>>,
+    Path(manga_id): Path<i64>,
+) -> Result<Json<Manga>, AppError> {
     sqlx::query!(
+        "UPDATE manga SET anilist_id = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        manga_id
+    )
+    .execute(&pool)
+    .await?;
+    sqlx::query!("DELETE FROM anilist_sync WHERE manga_id = ?", manga_id)
+        .execute(&pool)
+        .await?;
+
+    let manga = find_manga(&pool, manga_id)
+        .await?
+        .ok_or_else(|| anyhow!("Manga not found"))?;
+
+    Ok(Json(manga))
+}
+
+/// The series an ingest request is for: `manga_id` when given, otherwise the series
+/// linked to `anilist_id`, created from AniList metadata if there isn't one yet.
+pub async fn resolve_series(
+    pool: &Pool<Sqlite>,
+    state: &AppState,
+    manga_id: Option<i64>,
+    anilist_id: Option<i64>,
+) -> anyhow::Result<i64> {
+    if let Some(manga_id) = manga_id {
+        return sqlx::query_scalar!(r#"SELECT id as "id!" FROM manga WHERE id = ?"#, manga_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| anyhow!("Manga not found"));
+    }
+
+    let anilist_id = anilist_id.ok_or_else(|| anyhow!("manga_id or anilist_id is required"))?;
+    if let Some(manga_id) = sqlx::query_scalar!(
+        r#"SELECT id as "id!" FROM manga WHERE anilist_id = ?"#,
+        anilist_id
+    )
+    .fetch_optional(pool)
+    .await?
+    {
+        return Ok(manga_id);
+    }
+
+    let metadata = state.metadata.get(anilist_id).await?;
+    let manga_id = insert_series(
+        pool,
+        Some(anilist_id),
+        &metadata.title,
+        metadata.author.as_deref(),
+        metadata.description.as_deref(),
+    )
+    .await?;
+    anilist::save_details(pool, &state.fetcher, &state.image_dir, manga_id, &metadata).await?;
+
+    Ok(manga_id)
+}
+
+// The series an upload's ComicInfo.xml names, by title, or a new one that isn't on AniList
+async fn series_named(
+    pool: &Pool<Sqlite>,
+    title: &str,
+    author: Option<&str>,
+) -> anyhow::Result<i64> {
+    let existing = sqlx::query_scalar!(
+        r#"SELECT id as "id!" FROM manga WHERE title = ? COLLATE NOCASE ORDER BY id LIMIT 1"#,
+        title
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    match existing {
+        Some(manga_id) => Ok(manga_id),
+        None => insert_series(pool, None, title, author, None).await,
+    }
+}
+
+// New series are stored under their own id, which isn't known until the row exists
+async fn insert_series(
+    pool: &Pool<Sqlite>,
+    anilist_id: Option<i64>,
+    title: &str,
+    author: Option<&str>,
+    description: Option<&str>,
+) -> anyhow::Result<i64> {
+    let mut tx = pool.begin().await?;
+    let manga_id = sqlx::query_scalar!(
         r#"
-        INSERT INTO chapters (anilist_id, chapter_number, page_count, storage_path)
-        VALUES (?, ?, ?, ?)
-        ON CONFLICT(anilist_id, chapter_number) DO UPDATE SET
-            page_count = excluded.page_count,
-            storage_path = excluded.storage_path
+        INSERT INTO manga (anilist_id, title, author, description, storage_path)
+        VALUES (?, ?, ?, ?, '')
+        RETURNING id as "id!"
         "#,
         anilist_id,
-        chapter_number,
-        page_count,
-        chapter_storage_path
+        title,
+        author,
+        description
     )
-    .execute(&pool)
+    .fetch_one(&mut *tx)
     .await?;
+    sqlx::query!(
+        "UPDATE manga SET storage_path = 'data/manga/' || id WHERE id = ?",
+        manga_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
 
-    Ok(Json(manga))
+    Ok(manga_id)
 }
 
 // GET /manga - List all manga
 pub async fn list_manga(
     Extension(pool): Extension<Pool<Sqlite>>,
+    Query(filter): Query<LibraryFilter>,
 ) -> Result<Json<Vec<Manga>>, AppError> {
     let manga = sqlx::query_as!(
         Manga,
         r#"
-        SELECT anilist_id, title, author as "author?", description as "description?",
-                storage_path,
-               added_at as "added_at: String", updated_at as "updated_at: String"
+        SELECT id as "id!", anilist_id, title, author as "author?", description as "description?",
+               storage_path,
+               added_at as "added_at: String", updated_at as "updated_at: String",
+               native_title, synonyms as "synonyms: JsonColumn<Vec<String>>",
+               genres as "genres: JsonColumn<Vec<String>>", tags as "tags: JsonColumn<Vec<String>>",
+               status, total_chapters, total_volumes, start_year,
+               cover_url, cover_path, banner_url, banner_path,
+               title_locked as "title_locked: bool", author_locked as "author_locked: bool",
+               description_locked as "description_locked: bool"
         FROM manga
+        WHERE ?1 IS NULL OR anilist_id IN (SELECT anilist_id FROM anilist_list WHERE status = ?1)
         ORDER BY updated_at DESC
-        "#
+        "#,
+        filter.list
     )
     .fetch_all(&pool)
     .await?;
@@ -189,45 +545,148 @@ pub async fn list_manga(
     Ok(Json(manga))
 }
 
-// GET /manga/:anilist_id
+// GET /manga/:manga_id
 pub async fn get_manga(
     Extension(pool): Extension<Pool<Sqlite>>,
-    Path(anilist_id): Path<i64>,
+    Path(manga_id): Path<i64>,
 ) -> Result<Json<Manga>, AppError> {
+    let manga = find_manga(&pool, manga_id)
+        .await?
+        .ok_or_else(|| anyhow!("Manga not found"))?;
+
+    Ok(Json(manga))
+}
+
+async fn find_manga(pool: &Pool<Sqlite>, manga_id: i64) -> anyhow::Result<Option<Manga>> {
     let manga = sqlx::query_as!(
         Manga,
         r#"
-        SELECT anilist_id, title, author as "author?", description as "description?",
+        SELECT id as "id!", anilist_id, title, author as "author?", description as "description?",
                storage_path,
-               added_at as "added_at: String", updated_at as "updated_at: String"
+               added_at as "added_at: String", updated_at as "updated_at: String",
+               native_title, synonyms as "synonyms: JsonColumn<Vec<String>>",
+               genres as "genres: JsonColumn<Vec<String>>", tags as "tags: JsonColumn<Vec<String>>",
+               status, total_chapters, total_volumes, start_year,
+               cover_url, cover_path, banner_url, banner_path,
+               title_locked as "title_locked: bool", author_locked as "author_locked: bool",
+               description_locked as "description_locked: bool"
         FROM manga
-        WHERE anilist_id = ?
+        WHERE id = ?
         "#,
+        manga_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(manga)
+}
+
+// GET /manga/anilist/:anilist_id - The series linked to an AniList entry
+pub async fn get_manga_by_anilist(
+    Extension(pool): Extension<Pool<Sqlite>>,
+    Path(anilist_id): Path<i64>,
+) -> Result<Json<Manga>, AppError> {
+    let manga_id = linked_manga(&pool, anilist_id).await?;
+    get_manga(Extension(pool), Path(manga_id)).await
+}
+
+// Series ids and AniList ids overlap, so AniList ids are only ever looked up through here
+async fn linked_manga(pool: &Pool<Sqlite>, anilist_id: i64) -> anyhow::Result<i64> {
+    sqlx::query_scalar!(
+        r#"SELECT id as "id!" FROM manga WHERE anilist_id = ?"#,
         anilist_id
     )
-    .fetch_optional(&pool)
+    .fetch_optional(pool)
     .await?
-    .ok_or_else(|| anyhow!("Manga not found"))?;
+    .ok_or_else(|| anyhow!("Manga not found"))
+}
+
+// PUT /manga/:manga_id - Edit metadata by hand
+pub async fn update_manga(
+    Extension(pool): Extension<Pool<Sqlite>>,
+    Path(manga_id): Path<i64>,
+    Json(req): Json<UpdateMangaRequest>,
+) -> Result<Json<Manga>, AppError> {
+    if req.title.as_deref().is_some_and(|t| t.trim().is_empty()) {
+        return Err(anyhow!("title can't be empty").into());
+    }
+    let title = req.title.as_deref().map(str::trim);
+    let unlock_title = req.unlock.contains(&MangaField::Title);
+    let unlock_author = req.unlock.contains(&MangaField::Author);
+    let unlock_description = req.unlock.contains(&MangaField::Description);
+
+    let updated = sqlx::query!(
+        r#"
+        UPDATE manga
+        SET title = COALESCE(?1, title),
+            title_locked = CASE WHEN ?1 IS NOT NULL THEN 1 WHEN ?4 THEN 0 ELSE title_locked END,
+            author = COALESCE(?2, author),
+            author_locked = CASE WHEN ?2 IS NOT NULL THEN 1 WHEN ?5 THEN 0 ELSE author_locked END,
+            description = COALESCE(?3, description),
+            description_locked = CASE WHEN ?3 IS NOT NULL THEN 1 WHEN ?6 THEN 0
+                                      ELSE description_locked END,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?7
+        "#,
+        title,
+        req.author,
+        req.description,
+        unlock_title,
+        unlock_author,
+        unlock_description,
+        manga_id
+    )
+    .execute(&pool)
+    .await?
+    .rows_affected();
+    if updated == 0 {
+        return Err(anyhow!("Manga not found").into());
+    }
+
+    let manga = find_manga(&pool, manga_id)
+        .await?
+        .ok_or_else(|| anyhow!("Manga not found"))?;
 
     Ok(Json(manga))
 }
 
-// DELETE /manga/:anilist_id
-pub async fn delete_manga(
+// GET /manga/:manga_id/cover and /manga/:manga_id/banner
+pub async fn get_artwork(
     State(state): State<AppState>,
     Extension(pool): Extension<Pool<Sqlite>>,
-    Path(anilist_id): Path<i64>,
-) -> Result<StatusCode, AppError> {
-    // Get manga to find storage path
+    Path((manga_id, kind)): Path<(i64, String)>,
+) -> Result<impl IntoResponse, AppError> {
     let manga = sqlx::query!(
-        "SELECT storage_path FROM manga WHERE anilist_id = ?",
-        anilist_id
+        "SELECT cover_path, banner_path FROM manga WHERE id = ?",
+        manga_id
     )
     .fetch_optional(&pool)
     .await?
     .ok_or_else(|| anyhow!("Manga not found"))?;
 
-    sqlx::query!("DELETE FROM manga WHERE anilist_id = ?", anilist_id)
+    let path = match kind.as_str() {
+        "cover" => manga.cover_path,
+        "banner" => manga.banner_path,
+        _ => return Err(anyhow!("Unknown artwork {kind}").into()),
+    }
+    .ok_or_else(|| anyhow!("No {kind} stored for {manga_id}"))?;
+
+    Ok(image_response(&state.image_dir.join(path)).await?)
+}
+
+// DELETE /manga/:manga_id
+pub async fn delete_manga(
+    State(state): State<AppState>,
+    Extension(pool): Extension<Pool<Sqlite>>,
+    Path(manga_id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    // Get manga to find storage path
+    let manga = sqlx::query!("SELECT storage_path FROM manga WHERE id = ?", manga_id)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or_else(|| anyhow!("Manga not found"))?;
+
+    sqlx::query!("DELETE FROM manga WHERE id = ?", manga_id)
         .execute(&pool)
         .await?;
 
@@ -240,21 +699,33 @@ pub async fn delete_manga(
     Ok(StatusCode::NO_CONTENT)
 }
 
-// GET /manga/:anilist_id/chapters
-pub async fn list_chapters(
+// DELETE /manga/anilist/:anilist_id
+pub async fn delete_manga_by_anilist(
+    State(state): State<AppState>,
     Extension(pool): Extension<Pool<Sqlite>>,
     Path(anilist_id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    let manga_id = linked_manga(&pool, anilist_id).await?;
+    delete_manga(State(state), Extension(pool), Path(manga_id)).await
+}
+
+// GET /manga/:manga_id/chapters
+pub async fn list_chapters(
+    Extension(pool): Extension<Pool<Sqlite>>,
+    Path(manga_id): Path<i64>,
 ) -> Result<Json<Vec<Chapter>>, AppError> {
     let chapters = sqlx::query_as!(
         Chapter,
         r#"
-        SELECT id as "id!", anilist_id, chapter_number, title as "title?", page_count, storage_path,
-               added_at as "added_at: String"
+        SELECT id as "id!", manga_id, chapter_number, sub_chapter, title as "title?", page_count,
+               storage_path,
+               added_at as "added_at: String", source_url, volume, language,
+               reading_direction as "reading_direction: ReadingDirection"
         FROM chapters
-        WHERE anilist_id = ?
-        ORDER BY chapter_number ASC
+        WHERE manga_id = ?
+        ORDER BY chapter_number ASC, sub_chapter ASC
         "#,
-        anilist_id
+        manga_id
     )
     .fetch_all(&pool)
     .await?;
@@ -262,6 +733,213 @@ pub async fn list_chapters(
     Ok(Json(chapters))
 }
 
+// GET /manga/chapters/:chapter_id
+pub async fn get_chapter(
+    State(state): State<AppState>,
+    Extension(pool): Extension<Pool<Sqlite>>,
+    Path(chapter_id): Path<i64>,
+) -> Result<Json<ChapterDetails>, AppError> {
+    let chapter = find_chapter(&pool, chapter_id)
+        .await?
+        .ok_or_else(|| anyhow!("Chapter not found"))?;
+    let size = dir_size(&state.image_dir.join(&chapter.storage_path)).await?;
+
+    Ok(Json(ChapterDetails { chapter, size }))
+}
+
+// PUT /manga/chapters/:chapter_id - Renumber, retitle or move to another series
+pub async fn update_chapter(
+    State(state): State<AppState>,
+    Extension(pool): Extension<Pool<Sqlite>>,
+    Extension(sync): Extension<AniListSync>,
+    Path(chapter_id): Path<i64>,
+    Json(req): Json<UpdateChapterRequest>,
+) -> Result<Json<Chapter>, AppError> {
+    let chapter = find_chapter(&pool, chapter_id)
+        .await?
+        .ok_or_else(|| anyhow!("Chapter not found"))?;
+
+    let manga_id = req.manga_id.unwrap_or(chapter.manga_id);
+    let chapter_number = req.chapter_number.unwrap_or(chapter.chapter_number);
+    let sub_chapter = req.sub_chapter.unwrap_or_else(|| match req.chapter_number {
+        Some(number) => sub_chapter_of(number),
+        None => chapter.sub_chapter,
+    });
+    let manga_storage_path =
+        sqlx::query_scalar!("SELECT storage_path FROM manga WHERE id = ?", manga_id)
+            .fetch_optional(&pool)
+            .await?
+            .ok_or_else(|| anyhow!("Manga not found"))?;
+
+    let taken = sqlx::query_scalar!(
+        r#"
+        SELECT id as "id!" FROM chapters
+        WHERE manga_id = ? AND chapter_number = ? AND sub_chapter = ? AND id != ?
+        "#,
+        manga_id,
+        chapter_number,
+        sub_chapter,
+        chapter_id
+    )
+    .fetch_optional(&pool)
+    .await?;
+    let dir = chapter_dir(chapter_number, sub_chapter);
+    if taken.is_some() {
+        return Err(anyhow!("Manga {manga_id} already has a {dir}").into());
+    }
+
+    // Files move first and are put back if the database can't follow
+    let storage_path = format!("{}/{}", manga_storage_path, dir);
+    let old_path = state.image_dir.join(&chapter.storage_path);
+    let new_path = state.image_dir.join(&storage_path);
+    let moved = storage_path != chapter.storage_path;
+    if moved {
+        if fs::try_exists(&new_path).await? {
+            return Err(anyhow!("{storage_path} already exists").into());
+        }
+        if let Some(parent) = new_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::rename(&old_path, &new_path).await?;
+    }
+
+    let saved = move_chapter(
+        &pool,
+        chapter_id,
+        manga_id,
+        chapter_number,
+        sub_chapter,
+        req.title.as_deref(),
+        &storage_path,
+    )
+    .await;
+    if let Err(e) = saved {
+        if moved {
+            let _ = fs::rename(&new_path, &old_path).await;
+        }
+        return Err(e.into());
+    }
+
+    // Renumbering or moving can change either series' finished chapter count. Pushes
+    // only ever raise AniList's progress, so a lower count is left as it is there.
+    sync.enqueue(chapter.manga_id).await?;
+    if manga_id != chapter.manga_id {
+        sync.enqueue(manga_id).await?;
+    }
+
+    let chapter = find_chapter(&pool, chapter_id)
+        .await?
+        .ok_or_else(|| anyhow!("Chapter not found"))?;
+
+    Ok(Json(chapter))
+}
+
+async fn move_chapter(
+    pool: &Pool<Sqlite>,
+    chapter_id: i64,
+    manga_id: i64,
+    chapter_number: f64,
+    sub_chapter: i64,
+    title: Option<&str>,
+    storage_path: &str,
+) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query!(
+        r#"
+        UPDATE chapters
+        SET manga_id = ?1, chapter_number = ?2, sub_chapter = ?3,
+            title = CASE WHEN ?4 IS NULL THEN title ELSE NULLIF(?4, '') END, storage_path = ?5
+        WHERE id = ?6
+        "#,
+        manga_id,
+        chapter_number,
+        sub_chapter,
+        title,
+        storage_path,
+        chapter_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "UPDATE reading_progress SET manga_id = ? WHERE chapter_id = ?",
+        manga_id,
+        chapter_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "UPDATE manga SET updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        manga_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// DELETE /manga/chapters/:chapter_id - Drop one chapter and its pages
+pub async fn delete_chapter(
+    State(state): State<AppState>,
+    Extension(pool): Extension<Pool<Sqlite>>,
+    Extension(sync): Extension<AniListSync>,
+    Path(chapter_id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    let chapter = find_chapter(&pool, chapter_id)
+        .await?
+        .ok_or_else(|| anyhow!("Chapter not found"))?;
+
+    sqlx::query!("DELETE FROM chapters WHERE id = ?", chapter_id)
+        .execute(&pool)
+        .await?;
+    // Its reading progress went with it
+    sync.enqueue(chapter.manga_id).await?;
+
+    let chapter_path = state.image_dir.join(&chapter.storage_path);
+    if chapter_path.exists() {
+        fs::remove_dir_all(chapter_path).await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn find_chapter(pool: &Pool<Sqlite>, chapter_id: i64) -> anyhow::Result<Option<Chapter>> {
+    let chapter = sqlx::query_as!(
+        Chapter,
+        r#"
+        SELECT id as "id!", manga_id, chapter_number, sub_chapter, title as "title?", page_count,
+               storage_path,
+               added_at as "added_at: String", source_url, volume, language,
+               reading_direction as "reading_direction: ReadingDirection"
+        FROM chapters
+        WHERE id = ?
+        "#,
+        chapter_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(chapter)
+}
+
+// Total size of the files directly inside `path`; chapters keep their pages flat
+async fn dir_size(path: &std::path::Path) -> anyhow::Result<u64> {
+    let mut size = 0;
+    let mut entries = match fs::read_dir(path).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let metadata = entry.metadata().await?;
+        if metadata.is_file() {
+            size += metadata.len();
+        }
+    }
+
+    Ok(size)
+}
+
 // GET /chapters/:chapter_id/pages/:page_num
 pub async fn get_page(
     State(state): State<AppState>,
@@ -281,12 +959,11 @@ pub async fn get_page(
 
     while let Some(entry) = entries.next_entry().await? {
         let path = entry.path();
-        if path.is_file() {
-            if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                if is_image_file(filename) {
-                    image_files.push(path);
-                }
-            }
+        if path.is_file()
+            && let Some(filename) = path.file_name().and_then(|n| n.to_str())
+            && is_image_file(filename)
+        {
+            image_files.push(path);
         }
     }
 
@@ -299,13 +976,17 @@ pub async fn get_page(
 
     let page_path = &image_files[page_num - 1];
 
-    // Stream the image file
-    let file = File::open(page_path).await?;
+    Ok(image_response(page_path).await?)
+}
+
+// Streams an image file with a content type matching its extension
+async fn image_response(path: &std::path::Path) -> anyhow::Result<impl IntoResponse + use<>> {
+    let file = File::open(path).await?;
     let stream = tokio_util::io::ReaderStream::new(file);
     let body = axum::body::Body::from_stream(stream);
 
     // Determine content type from extension
-    let content_type = match page_path.extension().and_then(|e| e.to_str()) {
+    let content_type = match path.extension().and_then(|e| e.to_str()) {
         Some("jpg") | Some("jpeg") => "image/jpeg",
         Some("png") => "image/png",
         Some("gif") => "image/gif",
@@ -316,15 +997,6 @@ pub async fn get_page(
     Ok(([(axum::http::header::CONTENT_TYPE, content_type)], body))
 }
 
-fn is_image_file(filename: &str) -> bool {
-    let lower = filename.to_lowercase();
-    lower.ends_with(".jpg")
-        || lower.ends_with(".jpeg")
-        || lower.ends_with(".png")
-        || lower.ends_with(".gif")
-        || lower.ends_with(".webp")
-}
-
 #[derive(Debug)]
 pub struct AppError(anyhow::Error);
 
@@ -345,3 +1017,443 @@ impl IntoResponse for AppError {
         (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anilist::AniListClient;
+    use crate::db::test_pool;
+    use axum_test::multipart::{MultipartForm, Part};
+    use tempfile::TempDir;
+
+    async fn edit(pool: &Pool<Sqlite>, req: serde_json::Value) -> Result<Manga, AppError> {
+        let req = serde_json::from_value(req).unwrap();
+        update_manga(Extension(pool.clone()), Path(1), Json(req))
+            .await
+            .map(|Json(manga)| manga)
+    }
+
+    #[tokio::test]
+    async fn test_update_manga_locks_edited_fields() {
+        let pool = test_pool().await;
+        sqlx::query(
+            "INSERT INTO manga (id, anilist_id, title, author, storage_path) VALUES (1, 30013, 'One Piece', 'Eiichiro Oda', 'data/manga/1')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let manga = edit(&pool, serde_json::json!({ "title": " ワンピース " }))
+            .await
+            .unwrap();
+        assert_eq!(manga.title, "ワンピース");
+        assert_eq!(manga.author.as_deref(), Some("Eiichiro Oda"));
+        assert!(manga.title_locked);
+        assert!(!manga.author_locked && !manga.description_locked);
+
+        let manga = edit(
+            &pool,
+            serde_json::json!({ "description": "Pirates", "unlock": ["title"] }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(manga.title, "ワンピース");
+        assert!(!manga.title_locked);
+        assert!(manga.description_locked);
+
+        assert!(
+            edit(&pool, serde_json::json!({ "title": "  " }))
+                .await
+                .is_err()
+        );
+        sqlx::query("DELETE FROM manga")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(edit(&pool, serde_json::json!({})).await.is_err());
+    }
+
+    // Two series with one chapter on disk, read to the end
+    async fn library() -> (AppState, Pool<Sqlite>, [TempDir; 2]) {
+        let dirs = [TempDir::new().unwrap(), TempDir::new().unwrap()];
+        let state = AppState::new(dirs[0].path().to_path_buf(), dirs[1].path().to_path_buf());
+        let pool = test_pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO manga (id, title, storage_path) VALUES
+                (1, 'Oneshots', 'data/manga/1'),
+                (2, 'Doujin', 'data/manga/2');
+            INSERT INTO chapters (id, manga_id, chapter_number, page_count, storage_path, source_url)
+                VALUES (7, 1, 3, 2, 'data/manga/1/chapter_3', 'https://mangapill.com/chapters/7');
+            INSERT INTO reading_progress (manga_id, chapter_id, page, completed) VALUES (1, 7, 2, 1);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let chapter_dir = state.image_dir.join("data/manga/1/chapter_3");
+        std::fs::create_dir_all(&chapter_dir).unwrap();
+        std::fs::write(chapter_dir.join("001.jpg"), b"12345").unwrap();
+        std::fs::write(chapter_dir.join("002.jpg"), b"678").unwrap();
+        (state, pool, dirs)
+    }
+
+    async fn update(
+        state: &AppState,
+        pool: &Pool<Sqlite>,
+        req: serde_json::Value,
+    ) -> Result<Chapter, AppError> {
+        let sync = AniListSync::new(pool.clone(), AniListClient::new("http://127.0.0.1:9"));
+        update_chapter(
+            State(state.clone()),
+            Extension(pool.clone()),
+            Extension(sync),
+            Path(7),
+            Json(serde_json::from_value(req).unwrap()),
+        )
+        .await
+        .map(|Json(chapter)| chapter)
+    }
+
+    #[tokio::test]
+    async fn test_get_chapter() {
+        let (state, pool, _dirs) = library().await;
+
+        let Json(details) = get_chapter(State(state), Extension(pool), Path(7))
+            .await
+            .unwrap();
+
+        assert_eq!(details.chapter.page_count, 2);
+        assert_eq!(details.size, 8);
+        assert_eq!(
+            details.chapter.source_url.as_deref(),
+            Some("https://mangapill.com/chapters/7")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_move_chapter_to_other_series() {
+        let (state, pool, _dirs) = library().await;
+
+        let chapter = update(
+            &state,
+            &pool,
+            serde_json::json!({ "manga_id": 2, "chapter_number": 1, "title": "Extra" }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(chapter.manga_id, 2);
+        assert_eq!(chapter.storage_path, "data/manga/2/chapter_1");
+        assert_eq!(chapter.title.as_deref(), Some("Extra"));
+        assert!(
+            state
+                .image_dir
+                .join("data/manga/2/chapter_1/001.jpg")
+                .is_file()
+        );
+        assert!(!state.image_dir.join("data/manga/1/chapter_3").exists());
+        let progress: i64 = sqlx::query_scalar("SELECT manga_id FROM reading_progress")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(progress, 2);
+
+        let chapter = update(&state, &pool, serde_json::json!({ "title": "" }))
+            .await
+            .unwrap();
+        assert_eq!(chapter.title, None);
+    }
+
+    #[tokio::test]
+    async fn test_move_refuses_taken_number() {
+        let (state, pool, _dirs) = library().await;
+        sqlx::query(
+            "INSERT INTO chapters (manga_id, chapter_number, page_count, storage_path) VALUES (2, 1, 1, 'data/manga/2/chapter_1')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let moved = update(
+            &state,
+            &pool,
+            serde_json::json!({ "manga_id": 2, "chapter_number": 1 }),
+        )
+        .await;
+
+        assert!(moved.is_err());
+        assert!(
+            state
+                .image_dir
+                .join("data/manga/1/chapter_3/001.jpg")
+                .is_file()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_renumber_to_tenth_part() {
+        let (state, pool, _dirs) = library().await;
+        sqlx::query(
+            "INSERT INTO chapters (manga_id, chapter_number, sub_chapter, page_count, storage_path) VALUES (1, 12.1, 1, 1, 'data/manga/1/chapter_12.1')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // 12.10 is the same number as 12.1, but a different chapter
+        let chapter = update(
+            &state,
+            &pool,
+            serde_json::json!({ "chapter_number": 12.1, "sub_chapter": 10 }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(chapter.chapter_number, 12.1);
+        assert_eq!(chapter.sub_chapter, 10);
+        assert_eq!(chapter.storage_path, "data/manga/1/chapter_12.10");
+
+        let taken = update(&state, &pool, serde_json::json!({ "chapter_number": 12.1 })).await;
+        assert!(taken.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_chapter_edits_queue_sync() {
+        let (state, pool, _dirs) = library().await;
+        sqlx::query(
+            r#"
+            UPDATE manga SET anilist_id = 30013 WHERE id = 1;
+            INSERT INTO anilist_account (id, access_token, user_id, user_name)
+                VALUES (1, 'secret', 42, 'reader');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let sync = AniListSync::new(pool.clone(), AniListClient::new("http://127.0.0.1:9"));
+        let pending = || async { sync.status().await.unwrap().pending.len() };
+
+        // Renumbering within the series
+        update(&state, &pool, serde_json::json!({ "chapter_number": 4 }))
+            .await
+            .unwrap();
+        assert_eq!(pending().await, 1);
+
+        sqlx::query("DELETE FROM anilist_sync")
+            .execute(&pool)
+            .await
+            .unwrap();
+        delete_chapter(
+            State(state.clone()),
+            Extension(pool.clone()),
+            Extension(sync.clone()),
+            Path(7),
+        )
+        .await
+        .unwrap();
+        assert_eq!(pending().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_delete_chapter() {
+        let (state, pool, _dirs) = library().await;
+
+        let sync = AniListSync::new(pool.clone(), AniListClient::new("http://127.0.0.1:9"));
+        let status = delete_chapter(
+            State(state.clone()),
+            Extension(pool.clone()),
+            Extension(sync),
+            Path(7),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(!state.image_dir.join("data/manga/1/chapter_3").exists());
+        let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM reading_progress")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(left, 0);
+        assert!(find_manga(&pool, 1).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_anilist_routes_never_take_series_ids() {
+        let (state, pool, _dirs) = library().await;
+        // Series 2's id is also an AniList id, of a series that isn't stored here
+        sqlx::query("UPDATE manga SET anilist_id = 30013 WHERE id = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let server = server(&state, &pool);
+
+        let manga: serde_json::Value = server.get("/manga/anilist/30013").await.json();
+        assert_eq!(manga["id"], 1);
+        server
+            .delete("/manga/anilist/2")
+            .await
+            .assert_status_failure();
+        assert!(find_manga(&pool, 2).await.unwrap().is_some());
+
+        server
+            .delete("/manga/anilist/30013")
+            .await
+            .assert_status(StatusCode::NO_CONTENT);
+        assert!(find_manga(&pool, 1).await.unwrap().is_none());
+    }
+
+    fn server(state: &AppState, pool: &Pool<Sqlite>) -> axum_test::TestServer {
+        let queue = crate::jobs::JobQueue::new(pool.clone(), state.clone());
+        let sync = AniListSync::new(pool.clone(), AniListClient::new("http://127.0.0.1:9"));
+        axum_test::TestServer::new(crate::api::router(state.clone(), pool.clone(), queue, sync))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_upload_replaces_chapter() {
+        let (state, pool, _dirs) = library().await;
+        let server = server(&state, &pool);
+        let archives = TempDir::new().unwrap();
+
+        for pages in [&["001.jpg", "002.jpg", "003.jpg"][..], &["001.jpg"]] {
+            let entries: Vec<(&str, &[u8])> = pages.iter().map(|p| (*p, &b"page"[..])).collect();
+            let cbz = std::fs::read(ingest::tests::write_zip(archives.path(), &entries)).unwrap();
+            let form = MultipartForm::new()
+                .add_text("manga_id", "2")
+                .add_text("chapter_number", "4")
+                .add_part("file", Part::bytes(cbz).file_name("chapter.cbz"));
+
+            server
+                .post("/manga")
+                .multipart(form)
+                .await
+                .assert_status_ok();
+        }
+
+        let chapter_dir = state.image_dir.join("data/manga/2/chapter_4");
+        assert_eq!(std::fs::read_dir(chapter_dir).unwrap().count(), 1);
+        let page_count: i64 =
+            sqlx::query_scalar("SELECT page_count FROM chapters WHERE manga_id = 2")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(page_count, 1);
+        // Nothing is left behind once the upload is in place
+        let leftovers = std::fs::read_dir(state.image_dir.join(UPLOADS_DIR)).unwrap();
+        assert_eq!(leftovers.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_upload_rejects_odd_chapter_numbers() {
+        let (state, pool, _dirs) = library().await;
+        let server = server(&state, &pool);
+        let archives = TempDir::new().unwrap();
+        let cbz = std::fs::read(ingest::tests::write_zip(
+            archives.path(),
+            &[("001.jpg", &b"page"[..])],
+        ))
+        .unwrap();
+
+        for number in ["NaN", "inf", "-1", "12a"] {
+            let form = MultipartForm::new()
+                .add_text("manga_id", "2")
+                .add_text("chapter_number", number)
+                .add_part("file", Part::bytes(cbz.clone()).file_name("chapter.cbz"));
+            server
+                .post("/manga")
+                .multipart(form)
+                .await
+                .assert_status_failure();
+        }
+
+        // A tenth part keeps its sub-chapter
+        let form = MultipartForm::new()
+            .add_text("manga_id", "2")
+            .add_text("chapter_number", "12.10")
+            .add_part("file", Part::bytes(cbz).file_name("chapter.cbz"));
+        server
+            .post("/manga")
+            .multipart(form)
+            .await
+            .assert_status_ok();
+        let storage_path: String =
+            sqlx::query_scalar("SELECT storage_path FROM chapters WHERE manga_id = 2")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(storage_path, "data/manga/2/chapter_12.10");
+    }
+
+    #[tokio::test]
+    async fn test_upload_reads_comic_info() {
+        let (state, pool, _dirs) = library().await;
+        let server = server(&state, &pool);
+        let archives = TempDir::new().unwrap();
+
+        let uploads = [
+            // Everything from the archive, filed under the series it names
+            (
+                r#"<ComicInfo><Series>doujin</Series><Number>5</Number><Title>Extra</Title>
+                    <Volume>2</Volume><LanguageISO>ja</LanguageISO>
+                    <Manga>YesAndRightToLeft</Manga></ComicInfo>"#,
+                vec![],
+            ),
+            // Fields sent with the upload win, and an unknown series is created
+            (
+                r#"<ComicInfo><Series>New Series</Series><Writer>Someone</Writer>
+                    <Number>1</Number><Manga>YesAndRightToLeft</Manga></ComicInfo>"#,
+                vec![("chapter_number", "1.5"), ("reading_direction", "ltr")],
+            ),
+        ];
+        for (comic_info, fields) in uploads {
+            let path = ingest::tests::write_zip(
+                archives.path(),
+                &[
+                    ("ComicInfo.xml", comic_info.as_bytes()),
+                    ("001.jpg", b"page"),
+                ],
+            );
+            let mut form = MultipartForm::new();
+            for (name, value) in fields {
+                form = form.add_text(name, value);
+            }
+            let form = form.add_part(
+                "file",
+                Part::bytes(std::fs::read(path).unwrap()).file_name("chapter.cbz"),
+            );
+
+            server
+                .post("/manga")
+                .multipart(form)
+                .await
+                .assert_status_ok();
+        }
+
+        let chapters = sqlx::query!(
+            r#"
+            SELECT m.title as series, m.author, c.chapter_number, c.title, c.volume, c.language,
+                   c.reading_direction
+            FROM chapters c JOIN manga m ON m.id = c.manga_id
+            WHERE c.id != 7
+            ORDER BY c.id
+            "#
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(chapters.len(), 2);
+
+        assert_eq!(chapters[0].series, "Doujin");
+        assert_eq!(chapters[0].chapter_number, 5.0);
+        assert_eq!(chapters[0].title.as_deref(), Some("Extra"));
+        assert_eq!(chapters[0].volume, Some(2));
+        assert_eq!(chapters[0].language.as_deref(), Some("ja"));
+        assert_eq!(chapters[0].reading_direction.as_deref(), Some("rtl"));
+
+        assert_eq!(chapters[1].series, "New Series");
+        assert_eq!(chapters[1].author.as_deref(), Some("Someone"));
+        assert_eq!(chapters[1].chapter_number, 1.5);
+        assert_eq!(chapters[1].reading_direction.as_deref(), Some("ltr"));
+    }
+}
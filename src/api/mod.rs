@@ -1,16 +1,26 @@
 pub mod manga;
 pub mod pirate;
+pub mod progress;
 pub mod s3;
+pub mod status;
+pub mod sync;
 
 use crate::AppState;
+use crate::jobs::JobQueue;
+use crate::sync::AniListSync;
 use axum::{Extension, Router};
 use sqlx::{Pool, Sqlite};
 
-pub fn router(state: AppState, pool: Pool<Sqlite>) -> Router {
+pub fn router(state: AppState, pool: Pool<Sqlite>, queue: JobQueue, sync: AniListSync) -> Router {
     Router::new()
         .nest("/bucket", s3::router()) // Delete later lmfao
         .nest("/manga", manga::router())
         .nest("/pirate", pirate::router())
+        .nest("/progress", progress::router())
+        .nest("/status", status::router())
+        .nest("/sync", sync::router())
         .with_state(state)
         .layer(Extension(pool))
+        .layer(Extension(queue))
+        .layer(Extension(sync))
 }
@@ -1,61 +1,139 @@
-use crate::api::manga::AppError;
-use crate::arrrrr::{
-    Chapter, MangaResult, get_manga_pill_chapters, get_manga_pill_pages, search_manga_pill,
-};
-use crate::{AppState, anilist};
-use axum::extract::{Query, State};
+use crate::AppState;
+use crate::anilist::{MatchCandidate, confident_match};
+use crate::api::manga::{AppError, resolve_series};
+use crate::arrrrr::label::ChapterLabel;
+use crate::arrrrr::{Chapter, MangaResult, default_source};
+use crate::jobs::{DownloadJob, JobEvent, JobQueue, NewJob};
+use anyhow::anyhow;
+use axum::extract::{Path, Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::routing::{get, post};
 use axum::{Extension, Json, Router};
+use futures::stream::{self, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use sqlx::{Pool, Sqlite};
-use tokio::fs::{self, File};
-use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast::error::RecvError;
 
 pub fn router() -> Router<AppState> {
     Router::new()
+        .route("/sources", get(list_sources))
         .route("/search", get(search_manga))
         .route("/chapters", get(get_chapters))
+        .route("/match", post(match_series))
         .route("/download", post(download_chapter))
+        .route("/download/bulk", post(download_bulk))
+        .route("/jobs/{job_id}", get(get_job))
+        .route("/jobs/{job_id}/events", get(job_events))
+        .route("/jobs/{job_id}/retry", post(retry_job))
 }
 
 #[derive(Deserialize)]
 pub struct SearchRequest {
     pub query: String,
+    #[serde(default = "default_source")]
+    pub source: String,
 }
 
 #[derive(Deserialize)]
 pub struct ChaptersRequest {
     pub manga_url: String,
+    #[serde(default = "default_source")]
+    pub source: String,
+    pub language: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct DownloadRequest {
-    pub anilist_id: i64,
+    /// Series to file the chapters under. Without it the series linked to `anilist_id`
+    /// is used, and created from AniList when there isn't one yet. Leave both out to use
+    /// the series `manga_url` was last downloaded into or matched to.
+    pub manga_id: Option<i64>,
+    pub anilist_id: Option<i64>,
+    #[serde(default = "default_source")]
+    pub source: String,
+    /// Series page the chapter is listed on; its label there decides the chapter number.
+    /// Without it the number is read from `chapter_title`, and the series must be named.
+    pub manga_url: Option<String>,
     pub chapter_url: String,
-    pub chapter_number: f64,
+    /// The chapter's label as the source lists it, e.g. "Chapter 12: Title".
     pub chapter_title: Option<String>,
+    pub language: Option<String>,
+}
+
+/// A series as a source lists it, e.g. a search result.
+#[derive(Deserialize)]
+pub struct MatchRequest {
+    #[serde(default = "default_source")]
+    pub source: String,
+    #[serde(flatten)]
+    pub manga: MangaResult,
+}
+
+#[derive(Serialize)]
+pub struct MatchResponse {
+    pub candidates: Vec<MatchCandidate>,
+    /// The library series the source page is linked to, if any.
+    pub manga_id: Option<i64>,
+    /// Whether this request made the link.
+    pub linked: bool,
 }
 
 #[derive(Serialize)]
 pub struct DownloadResponse {
     pub success: bool,
     pub message: String,
-    pub pages_downloaded: usize,
+    pub job_id: i64,
+}
+
+/// Queues every chapter of `manga_url` that isn't stored or queued yet.
+/// `from` and `to` bound the chapter numbers inclusively; leave both out to fetch all missing.
+#[derive(Deserialize)]
+pub struct BulkDownloadRequest {
+    pub manga_id: Option<i64>,
+    pub anilist_id: Option<i64>,
+    #[serde(default = "default_source")]
+    pub source: String,
+    pub manga_url: String,
+    pub language: Option<String>,
+    pub from: Option<f64>,
+    pub to: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct BulkDownloadResponse {
+    pub success: bool,
+    pub message: String,
+    pub job_ids: Vec<i64>,
+    pub skipped: usize,
+}
+
+pub async fn list_sources(State(state): State<AppState>) -> Json<Vec<&'static str>> {
+    Json(state.sources.ids())
 }
 
 #[axum::debug_handler]
 pub async fn search_manga(
+    State(state): State<AppState>,
     Query(query): Query<SearchRequest>,
 ) -> Result<Json<Vec<MangaResult>>, AppError> {
-    let manga = search_manga_pill(&query.query).await?;
+    let manga = state
+        .sources
+        .get(&query.source)?
+        .search(&query.query)
+        .await?;
     Ok(Json(manga))
 }
 
 #[axum::debug_handler]
 pub async fn get_chapters(
+    State(state): State<AppState>,
     Query(query): Query<ChaptersRequest>,
 ) -> Result<Json<Vec<Chapter>>, AppError> {
-    let chapters = get_manga_pill_chapters(&query.manga_url).await?;
+    let chapters = state
+        .sources
+        .get(&query.source)?
+        .chapters(&query.manga_url, query.language.as_deref())
+        .await?;
     Ok(Json(chapters))
 }
 
@@ -63,99 +141,537 @@ pub async fn get_chapters(
 pub async fn download_chapter(
     State(state): State<AppState>,
     Extension(pool): Extension<Pool<Sqlite>>,
+    Extension(queue): Extension<JobQueue>,
     Json(req): Json<DownloadRequest>,
 ) -> Result<Json<DownloadResponse>, AppError> {
-    // Get pages from scraper
-    let pages = get_manga_pill_pages(&req.chapter_url).await?;
-
-    if pages.is_empty() {
-        return Ok(Json(DownloadResponse {
-            success: false,
-            message: "No pages found".to_string(),
-            pages_downloaded: 0,
-        }));
-    }
+    let source = state.sources.get(&req.source)?;
+    let (label, manga_id) = match &req.manga_url {
+        Some(manga_url) => {
+            let chapter = source
+                .chapters(manga_url, req.language.as_deref())
+                .await?
+                .into_iter()
+                .find(|c| c.url == req.chapter_url)
+                .ok_or_else(|| anyhow!("Chapter is not listed on {manga_url}"))?;
+            let manga_id = series_for(
+                &pool,
+                &state,
+                source.id(),
+                manga_url,
+                req.manga_id,
+                req.anilist_id,
+            )
+            .await?;
+            (chapter.label, manga_id)
+        }
+        // Older clients send only the chapter's label, along with a number of their own
+        // that is ignored in favour of parsing the label the same way listings are
+        None => {
+            let title = req
+                .chapter_title
+                .as_deref()
+                .ok_or_else(|| anyhow!("manga_url or chapter_title is required"))?;
+            let manga_id = resolve_series(&pool, &state, req.manga_id, req.anilist_id).await?;
+            (ChapterLabel::parse(title), manga_id)
+        }
+    };
+    let chapter_number = label
+        .number
+        .ok_or_else(|| anyhow!("No chapter number in the label of {}", req.chapter_url))?;
 
-    // Setup storage paths
-    let chapter_storage_path = format!(
-        "data/manga/{}/chapter_{}",
-        req.anilist_id, req.chapter_number
-    );
-    let mut full_chapter_path = state.image_dir.clone();
-    full_chapter_path.push(&chapter_storage_path);
-    fs::create_dir_all(&full_chapter_path).await?;
-
-    // Download pages
-    let client = reqwest::Client::builder()
-        .default_headers({
-            let mut headers = reqwest::header::HeaderMap::new();
-            headers.insert("Referer", "https://mangapill.com/".parse()?);
-            headers.insert(
-                "User-Agent",
-                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36".parse()?,
-            );
-            headers
+    let job_id = queue
+        .enqueue(NewJob {
+            manga_id,
+            source: source.id().to_string(),
+            chapter_url: req.chapter_url,
+            chapter_number,
+            sub_chapter: sub_chapter(&label),
+            chapter_title: label.title,
         })
-        .timeout(std::time::Duration::from_secs(30))
-        .build()?;
-
-    let mut downloaded = 0;
-    for (idx, page) in pages.iter().enumerate() {
-        let ext = page.url.split('.').last().unwrap_or("jpg");
-        let filename = format!("{:03}.{}", idx + 1, ext);
-        let mut file_path = full_chapter_path.clone();
-        file_path.push(&filename);
-
-        let response = client.get(&page.url).send().await?;
-        let bytes = response.bytes().await?;
-
-        let mut file = File::create(file_path).await?;
-        file.write_all(&bytes).await?;
-        downloaded += 1;
-        println!("Downloaded {filename}");
-    }
-    let (title, author, description) =
-        anilist::fetch_manga_metadata(req.anilist_id.clone()).await?;
-    let manga_storage_path = format!("data/manga/{}", req.anilist_id);
-    let page_count = downloaded as i64;
+        .await?;
 
-    sqlx::query!(
-        r#"
-        INSERT INTO manga (anilist_id, title, author,description, storage_path)
-        VALUES (?, ?, ?, ?, ?)
-        ON CONFLICT(anilist_id) DO UPDATE SET
-            updated_at = CURRENT_TIMESTAMP
-        "#,
+    Ok(Json(DownloadResponse {
+        success: true,
+        message: format!("Queued chapter {chapter_number} for download"),
+        job_id,
+    }))
+}
+
+#[axum::debug_handler]
+pub async fn download_bulk(
+    State(state): State<AppState>,
+    Extension(pool): Extension<Pool<Sqlite>>,
+    Extension(queue): Extension<JobQueue>,
+    Json(req): Json<BulkDownloadRequest>,
+) -> Result<Json<BulkDownloadResponse>, AppError> {
+    let source = state.sources.get(&req.source)?;
+    let chapters = source
+        .chapters(&req.manga_url, req.language.as_deref())
+        .await?;
+    let found = chapters.len();
+    let manga_id = series_for(
+        &pool,
+        &state,
+        source.id(),
+        &req.manga_url,
+        req.manga_id,
         req.anilist_id,
-        title,
-        author,
-        description,
-        manga_storage_path,
     )
-    .execute(&pool)
     .await?;
 
+    let mut have: Vec<(f64, i64)> = sqlx::query!(
+        "SELECT chapter_number, sub_chapter FROM chapters WHERE manga_id = ?",
+        manga_id
+    )
+    .fetch_all(&pool)
+    .await?
+    .into_iter()
+    .map(|c| (c.chapter_number, c.sub_chapter))
+    .collect();
+    have.extend(queue.pending_chapters(manga_id).await?);
+
+    let missing = select_missing(chapters, &have, req.from, req.to);
+    let mut job_ids = Vec::with_capacity(missing.len());
+    for ((number, sub_chapter), chapter) in missing {
+        let job_id = queue
+            .enqueue(NewJob {
+                manga_id,
+                source: source.id().to_string(),
+                chapter_url: chapter.url,
+                chapter_number: number,
+                sub_chapter,
+                chapter_title: chapter.label.title,
+            })
+            .await?;
+        job_ids.push(job_id);
+    }
+
+    Ok(Json(BulkDownloadResponse {
+        success: true,
+        message: format!("Queued {} of {} chapters", job_ids.len(), found),
+        skipped: found - job_ids.len(),
+        job_ids,
+    }))
+}
+
+// POST /pirate/match - Rank AniList entries for a source series, linking it when one stands out
+#[axum::debug_handler]
+pub async fn match_series(
+    State(state): State<AppState>,
+    Extension(pool): Extension<Pool<Sqlite>>,
+    Json(req): Json<MatchRequest>,
+) -> Result<Json<MatchResponse>, AppError> {
+    let source = state.sources.get(&req.source)?;
+    let candidates = state.anilist.match_title(&req.manga.title).await?;
+
+    // An existing link, automatic or chosen by the user, is never replaced here
+    let mut manga_id = linked_series(&pool, source.id(), &req.manga.url).await?;
+    let mut linked = false;
+    if manga_id.is_none()
+        && let Some(best) = confident_match(&candidates)
+    {
+        let id = resolve_series(&pool, &state, None, Some(best.anilist_id)).await?;
+        link_source(
+            &pool,
+            source.id(),
+            &req.manga.url,
+            id,
+            Some(best.confidence),
+        )
+        .await?;
+        manga_id = Some(id);
+        linked = true;
+    }
+
+    Ok(Json(MatchResponse {
+        candidates,
+        manga_id,
+        linked,
+    }))
+}
+
+// The series chapters from `manga_url` are filed under. A series named in the request
+// becomes the page's link for next time.
+async fn series_for(
+    pool: &Pool<Sqlite>,
+    state: &AppState,
+    source: &str,
+    manga_url: &str,
+    manga_id: Option<i64>,
+    anilist_id: Option<i64>,
+) -> anyhow::Result<i64> {
+    if manga_id.is_none() && anilist_id.is_none() {
+        return linked_series(pool, source, manga_url)
+            .await?
+            .ok_or_else(|| {
+                anyhow!("{manga_url} isn't linked to a series yet, pass manga_id or anilist_id")
+            });
+    }
+
+    let manga_id = resolve_series(pool, state, manga_id, anilist_id).await?;
+    link_source(pool, source, manga_url, manga_id, None).await?;
+    Ok(manga_id)
+}
+
+async fn linked_series(
+    pool: &Pool<Sqlite>,
+    source: &str,
+    manga_url: &str,
+) -> anyhow::Result<Option<i64>> {
+    let manga_id = sqlx::query_scalar!(
+        "SELECT manga_id FROM source_links WHERE source = ? AND manga_url = ?",
+        source,
+        manga_url
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(manga_id)
+}
+
+async fn link_source(
+    pool: &Pool<Sqlite>,
+    source: &str,
+    manga_url: &str,
+    manga_id: i64,
+    confidence: Option<f64>,
+) -> anyhow::Result<()> {
     sqlx::query!(
         r#"
-        INSERT INTO chapters (anilist_id, chapter_number, title, page_count, storage_path)
-        VALUES (?, ?, ?, ?, ?)
-        ON CONFLICT(anilist_id, chapter_number) DO UPDATE SET
-            title = excluded.title,
-            page_count = excluded.page_count,
-            storage_path = excluded.storage_path
+        INSERT INTO source_links (source, manga_url, manga_id, confidence)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT(source, manga_url) DO UPDATE SET
+            manga_id = excluded.manga_id,
+            confidence = excluded.confidence,
+            linked_at = CURRENT_TIMESTAMP
         "#,
-        req.anilist_id,
-        req.chapter_number,
-        req.chapter_title,
-        page_count,
-        chapter_storage_path
+        source,
+        manga_url,
+        manga_id,
+        confidence
     )
-    .execute(&pool)
+    .execute(pool)
     .await?;
 
-    Ok(Json(DownloadResponse {
-        success: true,
-        message: format!("Downloaded chapter {} successfully", req.chapter_number),
-        pages_downloaded: downloaded,
-    }))
+    Ok(())
+}
+
+// Chapters in range whose number isn't in `have`, lowest first. Unnumbered labels are dropped.
+fn select_missing(
+    chapters: Vec<Chapter>,
+    have: &[(f64, i64)],
+    from: Option<f64>,
+    to: Option<f64>,
+) -> Vec<((f64, i64), Chapter)> {
+    let mut missing: Vec<((f64, i64), Chapter)> = chapters
+        .into_iter()
+        .filter_map(|c| c.label.number.map(|n| ((n, sub_chapter(&c.label)), c)))
+        .filter(|((n, _), _)| from.is_none_or(|from| *n >= from) && to.is_none_or(|to| *n <= to))
+        .filter(|(key, _)| !have.contains(key))
+        .collect();
+
+    missing.sort_by(|a, b| a.0.0.total_cmp(&b.0.0).then(a.0.1.cmp(&b.0.1)));
+    missing.dedup_by(|a, b| a.0 == b.0);
+    missing
+}
+
+// Whole chapters are stored with sub-chapter 0
+fn sub_chapter(label: &ChapterLabel) -> i64 {
+    label.sub_chapter.map_or(0, i64::from)
+}
+
+#[axum::debug_handler]
+pub async fn get_job(
+    Extension(queue): Extension<JobQueue>,
+    Path(job_id): Path<i64>,
+) -> Result<Json<DownloadJob>, AppError> {
+    let job = queue
+        .get(job_id)
+        .await?
+        .ok_or_else(|| anyhow!("Job not found"))?;
+
+    Ok(Json(job))
+}
+
+// POST /pirate/jobs/:job_id/retry - Requeue a failed job, keeping the pages it already fetched
+#[axum::debug_handler]
+pub async fn retry_job(
+    Extension(queue): Extension<JobQueue>,
+    Path(job_id): Path<i64>,
+) -> Result<Json<DownloadJob>, AppError> {
+    if !queue.retry(job_id).await? {
+        return Err(anyhow!("Only failed jobs can be retried").into());
+    }
+    let job = queue
+        .get(job_id)
+        .await?
+        .ok_or_else(|| anyhow!("Job not found"))?;
+
+    Ok(Json(job))
+}
+
+// GET /pirate/jobs/:job_id/events - Current status, then progress until the job finishes
+pub async fn job_events(
+    Extension(queue): Extension<JobQueue>,
+    Path(job_id): Path<i64>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, AppError> {
+    // Subscribe before reading the row so nothing published in between is missed
+    let rx = queue.subscribe();
+    let job = queue
+        .get(job_id)
+        .await?
+        .ok_or_else(|| anyhow!("Job not found"))?;
+
+    let status = Event::default().event("status").json_data(&job);
+    let rx = (!job.status.is_terminal()).then_some(rx);
+
+    let updates = stream::unfold(rx, move |rx| async move {
+        let mut rx = rx?;
+        loop {
+            match rx.recv().await {
+                Ok(event) if event.job_id() == job_id => {
+                    let rx = (!event.is_terminal()).then_some(rx);
+                    return Some((event, rx));
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    .map(|event: JobEvent| Event::default().event(event.name()).json_data(&event));
+
+    Ok(Sse::new(stream::once(async { status }).chain(updates)).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anilist::AniListClient;
+    use crate::arrrrr::serve_fixtures;
+    use crate::db::test_pool;
+    use std::sync::{Arc, OnceLock};
+    use tempfile::TempDir;
+
+    fn chapters(labels: &[&str]) -> Vec<Chapter> {
+        labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                Chapter::new(
+                    label.to_string(),
+                    format!("https://mangapill.com/chapters/{i}"),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_select_missing_all() {
+        let listed = chapters(&["Chapter 3", "Chapter 2.5", "Chapter 2", "Chapter 1"]);
+
+        let missing = select_missing(listed, &[(2.0, 0)], None, None);
+        let numbers: Vec<f64> = missing.iter().map(|((n, _), _)| *n).collect();
+
+        assert_eq!(numbers, vec![1.0, 2.5, 3.0]);
+        assert_eq!(missing[0].1.chapter, "Chapter 1");
+    }
+
+    #[test]
+    fn test_select_missing_range_is_inclusive() {
+        let listed = chapters(&[
+            "Chapter 5",
+            "Chapter 4",
+            "Chapter 3",
+            "Chapter 2",
+            "Chapter 1",
+        ]);
+
+        let missing = select_missing(listed, &[(3.0, 0)], Some(2.0), Some(4.0));
+        let numbers: Vec<f64> = missing.iter().map(|((n, _), _)| *n).collect();
+
+        assert_eq!(numbers, vec![2.0, 4.0]);
+    }
+
+    #[test]
+    fn test_select_missing_drops_unnumbered_and_duplicates() {
+        let listed = chapters(&["Chapter 2", "Oneshot", "Chapter 2", "Chapter 1"]);
+
+        let missing = select_missing(listed, &[], None, None);
+        let numbers: Vec<f64> = missing.iter().map(|((n, _), _)| *n).collect();
+
+        assert_eq!(numbers, vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_select_missing_keeps_tenth_parts_apart() {
+        let listed = chapters(&["Chapter 12.10", "Chapter 12.1", "Chapter 12.11"]);
+
+        let missing = select_missing(listed, &[], None, None);
+        let keys: Vec<(f64, i64)> = missing.iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, vec![(12.1, 1), (12.1, 10), (12.11, 11)]);
+
+        // Having the first part doesn't count as having the tenth
+        let listed = chapters(&["Chapter 12.10", "Chapter 12.1"]);
+        let missing = select_missing(listed, &[(12.1, 1)], None, None);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].1.chapter, "Chapter 12.10");
+    }
+
+    // AniList knows one confident match for "Chainsaw Man". Artwork URLs point back at
+    // the stub, which has none, so nothing leaves the machine.
+    async fn matching_state() -> (AppState, [TempDir; 2]) {
+        let base = Arc::new(OnceLock::<String>::new());
+        let own_base = base.clone();
+        let router = axum::Router::new().route(
+            "/",
+            post(move |Json(body): Json<serde_json::Value>| {
+                let own_base = own_base.clone();
+                async move {
+                    let query = body["query"].as_str().unwrap_or_default();
+                    let body = if query.contains("Page(") {
+                        include_str!("../anilist/fixtures/search.json")
+                    } else {
+                        include_str!("../anilist/fixtures/media.json")
+                    };
+                    body.replace("https://s4.anilist.co", own_base.get().unwrap())
+                }
+            }),
+        );
+        let endpoint = serve_fixtures(router).await;
+        base.set(endpoint.clone()).unwrap();
+        let dirs = [TempDir::new().unwrap(), TempDir::new().unwrap()];
+        let state = AppState::new(dirs[0].path().to_path_buf(), dirs[1].path().to_path_buf())
+            .with_anilist(AniListClient::new(&endpoint));
+        (state, dirs)
+    }
+
+    fn match_request(title: &str, url: &str) -> Json<MatchRequest> {
+        Json(MatchRequest {
+            source: default_source(),
+            manga: MangaResult {
+                title: title.to_string(),
+                url: url.to_string(),
+                thumbnail: None,
+            },
+        })
+    }
+
+    #[tokio::test]
+    async fn test_match_links_confident_match() {
+        let (state, _dirs) = matching_state().await;
+        let pool = test_pool().await;
+        let url = "https://mangapill.com/manga/3/chainsaw-man";
+
+        let Json(matched) = match_series(
+            State(state.clone()),
+            Extension(pool.clone()),
+            match_request("Chainsaw Man", url),
+        )
+        .await
+        .unwrap();
+
+        assert!(matched.linked);
+        assert_eq!(matched.candidates[0].anilist_id, 105778);
+        let manga_id = matched.manga_id.unwrap();
+        let anilist_id = sqlx::query_scalar!("SELECT anilist_id FROM manga WHERE id = ?", manga_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(anilist_id, Some(105778));
+
+        // Downloads from the page no longer need the series spelled out
+        let series = series_for(&pool, &state, "mangapill", url, None, None)
+            .await
+            .unwrap();
+        assert_eq!(series, manga_id);
+    }
+
+    #[tokio::test]
+    async fn test_match_without_confident_candidate_only_ranks() {
+        let (state, _dirs) = matching_state().await;
+        let pool = test_pool().await;
+
+        let Json(matched) = match_series(
+            State(state),
+            Extension(pool.clone()),
+            match_request("Chainsaw", "https://mangapill.com/manga/9/chainsaw"),
+        )
+        .await
+        .unwrap();
+
+        assert!(!matched.linked);
+        assert_eq!(matched.manga_id, None);
+        assert_eq!(matched.candidates.len(), 3);
+        let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM manga")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, 0);
+    }
+
+    #[tokio::test]
+    async fn test_download_accepts_app_payload() {
+        let (state, _dirs) = matching_state().await;
+        let pool = test_pool().await;
+        sqlx::query(
+            "INSERT INTO manga (id, anilist_id, title, storage_path) VALUES (7, 105778, 'Chainsaw Man', 'data/manga/7')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let queue = JobQueue::new(pool.clone(), state.clone());
+
+        // What the app sends, chapter_number included, and no manga_url
+        let req = serde_json::from_value(serde_json::json!({
+            "anilist_id": 105778,
+            "chapter_url": "https://mangapill.com/chapters/3-10012000/chainsaw-man-chapter-12",
+            "chapter_number": 0,
+            "chapter_title": "Chapter 12.5: Kill Denji",
+        }))
+        .unwrap();
+        let Json(res) = download_chapter(
+            State(state),
+            Extension(pool),
+            Extension(queue.clone()),
+            Json(req),
+        )
+        .await
+        .unwrap();
+
+        let job = queue.get(res.job_id).await.unwrap().unwrap();
+        assert_eq!(job.manga_id, 7);
+        assert_eq!(job.chapter_number, 12.5);
+        assert_eq!(job.chapter_title.as_deref(), Some("Kill Denji"));
+    }
+
+    #[tokio::test]
+    async fn test_explicit_series_replaces_link() {
+        let (state, _dirs) = matching_state().await;
+        let pool = test_pool().await;
+        sqlx::query(
+            "INSERT INTO manga (id, title, storage_path) VALUES (1, 'A', 'data/manga/1'), (2, 'B', 'data/manga/2')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let url = "https://mangapill.com/manga/5/b";
+
+        assert!(
+            series_for(&pool, &state, "mangapill", url, None, None)
+                .await
+                .is_err()
+        );
+        series_for(&pool, &state, "mangapill", url, Some(1), None)
+            .await
+            .unwrap();
+        series_for(&pool, &state, "mangapill", url, Some(2), None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            linked_series(&pool, "mangapill", url).await.unwrap(),
+            Some(2)
+        );
+        assert_eq!(linked_series(&pool, "mangadex", url).await.unwrap(), None);
+    }
 }
@@ -0,0 +1,470 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use axum::extract::Path;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite, prelude::FromRow};
+
+use crate::AppState;
+use crate::api::manga::AppError;
+use crate::sync::AniListSync;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_progress).post(update_progress))
+        .route("/continue", get(continue_reading))
+        .route("/{manga_id}", get(get_manga_progress))
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ReadingProgress {
+    pub manga_id: i64,
+    pub chapter_id: i64,
+    pub chapter_number: f64,
+    pub page: i64,
+    pub completed: bool,
+    pub updated_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateProgressRequest {
+    pub chapter_id: i64,
+    pub page: i64,
+    /// Left out, a chapter counts as completed once its last page is reached and stays so.
+    pub completed: Option<bool>,
+}
+
+/// Where to pick a series back up.
+#[derive(Debug, Serialize)]
+pub struct ContinueReading {
+    pub manga_id: i64,
+    pub title: String,
+    pub chapter_id: i64,
+    pub chapter_number: f64,
+    pub page: i64,
+    pub page_count: i64,
+    pub last_read_at: String,
+}
+
+#[derive(Serialize)]
+pub struct MangaProgress {
+    pub manga_id: i64,
+    pub chapters: Vec<ReadingProgress>,
+    /// None when nothing has been read yet or every stored chapter is finished.
+    pub continue_reading: Option<ContinueReading>,
+}
+
+#[derive(Debug, FromRow)]
+struct StoredChapter {
+    id: i64,
+    manga_id: i64,
+    chapter_number: f64,
+    page_count: i64,
+}
+
+// GET /progress - Everything read, most recent first
+pub async fn list_progress(
+    Extension(pool): Extension<Pool<Sqlite>>,
+) -> Result<Json<Vec<ReadingProgress>>, AppError> {
+    let progress = sqlx::query_as!(
+        ReadingProgress,
+        r#"
+        SELECT p.manga_id, p.chapter_id, c.chapter_number, p.page, p.completed,
+               p.updated_at as "updated_at: String"
+        FROM reading_progress p
+        JOIN chapters c ON c.id = p.chapter_id
+        ORDER BY p.updated_at DESC, c.chapter_number DESC
+        "#
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(progress))
+}
+
+// POST /progress - Record the page a chapter was left on
+pub async fn update_progress(
+    Extension(pool): Extension<Pool<Sqlite>>,
+    Extension(sync): Extension<AniListSync>,
+    Json(req): Json<UpdateProgressRequest>,
+) -> Result<Json<ReadingProgress>, AppError> {
+    let chapter = sqlx::query!(
+        "SELECT manga_id, page_count FROM chapters WHERE id = ?",
+        req.chapter_id
+    )
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| anyhow!("Chapter not found"))?;
+
+    if req.page < 1 || req.page > chapter.page_count {
+        return Err(anyhow!("Page number out of range").into());
+    }
+    let reached_end = req.page == chapter.page_count;
+
+    let was_completed = sqlx::query_scalar!(
+        "SELECT completed FROM reading_progress WHERE chapter_id = ?",
+        req.chapter_id
+    )
+    .fetch_optional(&pool)
+    .await?
+    .unwrap_or(false);
+
+    // Millisecond timestamps so progress saved within the same second still orders correctly
+    sqlx::query!(
+        r#"
+        INSERT INTO reading_progress (manga_id, chapter_id, page, completed, updated_at)
+        VALUES (?, ?, ?, COALESCE(?, ?), strftime('%Y-%m-%d %H:%M:%f', 'now'))
+        ON CONFLICT(manga_id, chapter_id) DO UPDATE SET
+            page = excluded.page,
+            completed = COALESCE(?, reading_progress.completed OR excluded.completed),
+            updated_at = excluded.updated_at
+        "#,
+        chapter.manga_id,
+        req.chapter_id,
+        req.page,
+        req.completed,
+        reached_end,
+        req.completed
+    )
+    .execute(&pool)
+    .await?;
+
+    let progress = sqlx::query_as!(
+        ReadingProgress,
+        r#"
+        SELECT p.manga_id, p.chapter_id, c.chapter_number, p.page, p.completed,
+               p.updated_at as "updated_at: String"
+        FROM reading_progress p
+        JOIN chapters c ON c.id = p.chapter_id
+        WHERE p.chapter_id = ?
+        "#,
+        req.chapter_id
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    if progress.completed && !was_completed {
+        sync.enqueue(progress.manga_id).await?;
+    }
+
+    Ok(Json(progress))
+}
+
+// GET /progress/:manga_id
+pub async fn get_manga_progress(
+    Extension(pool): Extension<Pool<Sqlite>>,
+    Path(manga_id): Path<i64>,
+) -> Result<Json<MangaProgress>, AppError> {
+    let title = sqlx::query_scalar!("SELECT title FROM manga WHERE id = ?", manga_id)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or_else(|| anyhow!("Manga not found"))?;
+
+    let chapters = sqlx::query_as!(
+        ReadingProgress,
+        r#"
+        SELECT p.manga_id, p.chapter_id, c.chapter_number, p.page, p.completed,
+               p.updated_at as "updated_at: String"
+        FROM reading_progress p
+        JOIN chapters c ON c.id = p.chapter_id
+        WHERE p.manga_id = ?
+        ORDER BY c.chapter_number ASC
+        "#,
+        manga_id
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let stored = sqlx::query_as!(
+        StoredChapter,
+        r#"
+        SELECT id as "id!", manga_id, chapter_number, page_count
+        FROM chapters
+        WHERE manga_id = ?
+        ORDER BY chapter_number ASC
+        "#,
+        manga_id
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let continue_reading =
+        resume_point(&stored, &chapters).map(|(chapter, page, last_read_at)| ContinueReading {
+            manga_id,
+            title,
+            chapter_id: chapter.id,
+            chapter_number: chapter.chapter_number,
+            page,
+            page_count: chapter.page_count,
+            last_read_at,
+        });
+
+    Ok(Json(MangaProgress {
+        manga_id,
+        chapters,
+        continue_reading,
+    }))
+}
+
+// GET /progress/continue - The next place to read in every started series, most recent first
+pub async fn continue_reading(
+    Extension(pool): Extension<Pool<Sqlite>>,
+) -> Result<Json<Vec<ContinueReading>>, AppError> {
+    let progress = sqlx::query_as!(
+        ReadingProgress,
+        r#"
+        SELECT p.manga_id, p.chapter_id, c.chapter_number, p.page, p.completed,
+               p.updated_at as "updated_at: String"
+        FROM reading_progress p
+        JOIN chapters c ON c.id = p.chapter_id
+        "#
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let stored = sqlx::query_as!(
+        StoredChapter,
+        r#"
+        SELECT id as "id!", manga_id, chapter_number, page_count
+        FROM chapters
+        WHERE manga_id IN (SELECT manga_id FROM reading_progress)
+        ORDER BY chapter_number ASC
+        "#
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let titles: HashMap<i64, String> = sqlx::query!(
+        r#"
+        SELECT id as "id!", title FROM manga
+        WHERE id IN (SELECT manga_id FROM reading_progress)
+        "#
+    )
+    .fetch_all(&pool)
+    .await?
+    .into_iter()
+    .map(|m| (m.id, m.title))
+    .collect();
+
+    let mut by_manga: HashMap<i64, (Vec<StoredChapter>, Vec<ReadingProgress>)> = HashMap::new();
+    for chapter in stored {
+        by_manga
+            .entry(chapter.manga_id)
+            .or_default()
+            .0
+            .push(chapter);
+    }
+    for row in progress {
+        by_manga.entry(row.manga_id).or_default().1.push(row);
+    }
+
+    let mut resume: Vec<ContinueReading> = by_manga
+        .into_iter()
+        .filter_map(|(manga_id, (chapters, progress))| {
+            let (chapter, page, last_read_at) = resume_point(&chapters, &progress)?;
+            Some(ContinueReading {
+                manga_id,
+                title: titles.get(&manga_id)?.clone(),
+                chapter_id: chapter.id,
+                chapter_number: chapter.chapter_number,
+                page,
+                page_count: chapter.page_count,
+                last_read_at,
+            })
+        })
+        .collect();
+    resume.sort_by(|a, b| b.last_read_at.cmp(&a.last_read_at));
+
+    Ok(Json(resume))
+}
+
+// The chapter and page to resume a series at, given its stored chapters in reading order.
+// An unfinished latest chapter is resumed in place; a finished one moves on to the
+// next chapter not completed yet.
+fn resume_point<'a>(
+    chapters: &'a [StoredChapter],
+    progress: &[ReadingProgress],
+) -> Option<(&'a StoredChapter, i64, String)> {
+    let last = progress.iter().max_by(|a, b| {
+        a.updated_at
+            .cmp(&b.updated_at)
+            .then(a.chapter_number.total_cmp(&b.chapter_number))
+    })?;
+
+    let chapter = if last.completed {
+        chapters.iter().find(|c| {
+            c.chapter_number > last.chapter_number
+                && !progress.iter().any(|p| p.chapter_id == c.id && p.completed)
+        })?
+    } else {
+        chapters.iter().find(|c| c.id == last.chapter_id)?
+    };
+
+    let page = progress
+        .iter()
+        .find(|p| p.chapter_id == chapter.id && !p.completed)
+        .map_or(1, |p| p.page);
+
+    Some((chapter, page, last.updated_at.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anilist::AniListClient;
+    use crate::db::test_pool;
+
+    fn stored(numbers: &[f64]) -> Vec<StoredChapter> {
+        numbers
+            .iter()
+            .enumerate()
+            .map(|(i, n)| StoredChapter {
+                id: i as i64 + 1,
+                manga_id: 1,
+                chapter_number: *n,
+                page_count: 20,
+            })
+            .collect()
+    }
+
+    fn read(chapter_id: i64, chapter_number: f64, page: i64, at: &str) -> ReadingProgress {
+        ReadingProgress {
+            manga_id: 1,
+            chapter_id,
+            chapter_number,
+            page,
+            completed: page == 20,
+            updated_at: format!("2025-12-01 10:{at}"),
+        }
+    }
+
+    #[test]
+    fn test_resume_unfinished_chapter() {
+        let chapters = stored(&[1.0, 2.0, 3.0]);
+        let progress = [read(1, 1.0, 20, "00:00"), read(2, 2.0, 7, "05:00")];
+
+        let (chapter, page, _) = resume_point(&chapters, &progress).unwrap();
+        assert_eq!(chapter.id, 2);
+        assert_eq!(page, 7);
+    }
+
+    #[test]
+    fn test_resume_moves_past_finished_chapters() {
+        let chapters = stored(&[1.0, 2.0, 2.5, 3.0]);
+        // Chapter 2.5 was read earlier, out of order
+        let progress = [
+            read(3, 2.5, 20, "00:00"),
+            read(1, 1.0, 20, "01:00"),
+            read(2, 2.0, 20, "02:00"),
+        ];
+
+        let (chapter, page, last_read_at) = resume_point(&chapters, &progress).unwrap();
+        assert_eq!(chapter.chapter_number, 3.0);
+        assert_eq!(page, 1);
+        assert_eq!(last_read_at, "2025-12-01 10:02:00");
+    }
+
+    #[test]
+    fn test_resume_nothing_left() {
+        let chapters = stored(&[1.0, 2.0]);
+        let progress = [read(1, 1.0, 20, "00:00"), read(2, 2.0, 20, "01:00")];
+        assert!(resume_point(&chapters, &progress).is_none());
+        assert!(resume_point(&chapters, &[]).is_none());
+    }
+
+    async fn seeded_pool() -> Pool<Sqlite> {
+        let pool = test_pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO manga (id, anilist_id, title, storage_path) VALUES (30013, 30013, 'One Piece', 'data/manga/30013');
+            INSERT INTO chapters (id, manga_id, chapter_number, page_count, storage_path) VALUES
+                (1, 30013, 1, 3, 'data/manga/30013/chapter_1'),
+                (2, 30013, 2, 3, 'data/manga/30013/chapter_2');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool
+    }
+
+    // No account is linked, so nothing is ever sent here
+    fn unlinked_sync(pool: &Pool<Sqlite>) -> AniListSync {
+        AniListSync::new(pool.clone(), AniListClient::new("http://127.0.0.1:9"))
+    }
+
+    async fn save(pool: &Pool<Sqlite>, chapter_id: i64, page: i64, completed: Option<bool>) {
+        let _ = update_progress(
+            Extension(pool.clone()),
+            Extension(unlinked_sync(pool)),
+            Json(UpdateProgressRequest {
+                chapter_id,
+                page,
+                completed,
+            }),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_update_and_continue() {
+        let pool = seeded_pool().await;
+
+        save(&pool, 1, 3, None).await;
+        save(&pool, 2, 2, None).await;
+
+        let Json(manga) = get_manga_progress(Extension(pool.clone()), Path(30013))
+            .await
+            .unwrap();
+        assert_eq!(manga.chapters.len(), 2);
+        assert!(manga.chapters[0].completed);
+        assert!(!manga.chapters[1].completed);
+        let resume = manga.continue_reading.unwrap();
+        assert_eq!((resume.chapter_id, resume.page), (2, 2));
+
+        let Json(all) = continue_reading(Extension(pool.clone())).await.unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].title, "One Piece");
+
+        save(&pool, 2, 3, None).await;
+        let Json(all) = continue_reading(Extension(pool)).await.unwrap();
+        assert!(all.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_completion_is_sticky_unless_cleared() {
+        let pool = seeded_pool().await;
+
+        save(&pool, 1, 3, None).await;
+        save(&pool, 1, 1, None).await;
+        let Json(progress) = list_progress(Extension(pool.clone())).await.unwrap();
+        assert!(progress[0].completed);
+        assert_eq!(progress[0].page, 1);
+
+        save(&pool, 1, 1, Some(false)).await;
+        let Json(progress) = list_progress(Extension(pool)).await.unwrap();
+        assert!(!progress[0].completed);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_page_out_of_range() {
+        let pool = seeded_pool().await;
+
+        for page in [0, 4] {
+            let result = update_progress(
+                Extension(pool.clone()),
+                Extension(unlinked_sync(&pool)),
+                Json(UpdateProgressRequest {
+                    chapter_id: 1,
+                    page,
+                    completed: None,
+                }),
+            )
+            .await;
+            assert!(result.is_err());
+        }
+    }
+}
@@ -81,7 +81,7 @@ pub async fn put_obj(
         fs::create_dir_all(parent).await?;
     }
     let mut f = File::create(&p).await?;
-    f.write(&body).await?;
+    f.write_all(&body).await?;
     f.flush().await?;
     state
         .kv_store
@@ -98,7 +98,7 @@ pub async fn get_obj(
 ) -> Result<impl IntoResponse, AppError> {
     let raw = state
         .kv_store
-        .get(&format!("{bucket}/{key}").as_bytes())
+        .get(format!("{bucket}/{key}").as_bytes())
         .await
         .ok_or_else(|| anyhow!("object does not exist"))?;
 
@@ -137,7 +137,7 @@ pub async fn delete_obj(
 ) -> Result<String, AppError> {
     let raw = state
         .kv_store
-        .get(&format!("{bucket}/{key}").as_bytes())
+        .get(format!("{bucket}/{key}").as_bytes())
         .await
         .ok_or_else(|| anyhow!("object does not exist"))?;
 
@@ -158,7 +158,7 @@ pub async fn delete_obj(
     let _ = fs::remove_dir(p).await; // Ignore result error-will fail if dir is not empty, should be ok
     state
         .kv_store
-        .remove(&format!("{bucket}/{key}").as_bytes())
+        .remove(format!("{bucket}/{key}").as_bytes())
         .await;
     Ok(json!({"message":"deleted successfully"}).to_string())
 }
@@ -173,7 +173,7 @@ pub async fn new_bucket(
     headers: HeaderMap,
     Path(bucket): Path<String>,
 ) -> Result<String, AppError> {
-    if state.kv_store.get(&bucket.as_bytes()).await.is_some() {
+    if state.kv_store.get(bucket.as_bytes()).await.is_some() {
         return Err(anyhow!("bucket already exists").into());
     };
 
@@ -190,7 +190,7 @@ pub async fn new_bucket(
     state
         .kv_store
         .put(
-            format!("{bucket}"),
+            bucket.to_string(),
             bincode::encode_to_vec(&metadata, bincode::config::standard())?,
         )
         .await;
@@ -202,7 +202,7 @@ pub async fn get_bucket(
 ) -> Result<String, AppError> {
     let raw = state
         .kv_store
-        .get(&bucket.as_bytes())
+        .get(bucket.as_bytes())
         .await
         .ok_or_else(|| anyhow!("no bucket exists"))?;
 
@@ -0,0 +1,27 @@
+use crate::AppState;
+use crate::api::manga::AppError;
+use crate::db::{self, SchemaStatus};
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/", get(get_status))
+}
+
+#[derive(Serialize)]
+pub struct StatusResponse {
+    pub version: &'static str,
+    pub schema: SchemaStatus,
+}
+
+// GET /status
+pub async fn get_status(
+    Extension(pool): Extension<Pool<Sqlite>>,
+) -> Result<Json<StatusResponse>, AppError> {
+    Ok(Json(StatusResponse {
+        version: env!("CARGO_PKG_VERSION"),
+        schema: db::schema_status(&pool).await?,
+    }))
+}
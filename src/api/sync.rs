@@ -0,0 +1,91 @@
+use crate::AppState;
+use crate::api::manga::AppError;
+use crate::sync::{Account, AniListSync, ListEntry, ListStatus, SyncStatus};
+use anyhow::anyhow;
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use axum::routing::{get, post, put};
+use axum::{Extension, Json, Router};
+use serde::{Deserialize, Serialize};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/token", put(set_token).delete(clear_token))
+        .route("/status", get(get_status))
+        .route("/anilist", post(sync_all))
+        .route("/list", get(get_list))
+        .route("/manga/{manga_id}", post(sync_manga))
+}
+
+#[derive(Deserialize)]
+pub struct TokenRequest {
+    pub access_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ListQuery {
+    pub status: Option<ListStatus>,
+}
+
+#[derive(Serialize)]
+pub struct SyncResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+// PUT /sync/token - Link the AniList account progress is pushed to
+pub async fn set_token(
+    Extension(sync): Extension<AniListSync>,
+    Json(req): Json<TokenRequest>,
+) -> Result<Json<Account>, AppError> {
+    Ok(Json(sync.set_token(&req.access_token).await?))
+}
+
+// DELETE /sync/token
+pub async fn clear_token(Extension(sync): Extension<AniListSync>) -> Result<StatusCode, AppError> {
+    sync.clear_token().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// GET /sync/status - Linked account plus pushes still pending or given up on
+pub async fn get_status(
+    Extension(sync): Extension<AniListSync>,
+) -> Result<Json<SyncStatus>, AppError> {
+    Ok(Json(sync.status().await?))
+}
+
+// POST /sync/anilist - Pull the AniList list, then push progress for every manga read past it
+pub async fn sync_all(
+    Extension(sync): Extension<AniListSync>,
+) -> Result<Json<SyncResponse>, AppError> {
+    let pulled = sync.pull_list().await?;
+    let queued = sync.enqueue_all().await?;
+
+    Ok(Json(SyncResponse {
+        success: true,
+        message: format!("Pulled {pulled} list entries, queued {queued} manga for sync"),
+    }))
+}
+
+// GET /sync/list?status=reading - The AniList list as last pulled
+pub async fn get_list(
+    Extension(sync): Extension<AniListSync>,
+    Query(query): Query<ListQuery>,
+) -> Result<Json<Vec<ListEntry>>, AppError> {
+    Ok(Json(sync.list(query.status).await?))
+}
+
+// POST /sync/manga/:manga_id
+pub async fn sync_manga(
+    Extension(sync): Extension<AniListSync>,
+    Path(manga_id): Path<i64>,
+) -> Result<Json<SyncResponse>, AppError> {
+    if !sync.enqueue(manga_id).await? {
+        return Err(anyhow!("No AniList account linked, or the series isn't on AniList").into());
+    }
+
+    Ok(Json(SyncResponse {
+        success: true,
+        message: format!("Queued {manga_id} for sync"),
+    }))
+}
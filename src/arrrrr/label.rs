@@ -0,0 +1,238 @@
+use serde::Serialize;
+
+// Words that mark a chapter as bonus material rather than part of the main run
+const EXTRA_MARKERS: [&str; 5] = ["extra", "omake", "special", "bonus", "side story"];
+
+/// What can be read out of a free-text chapter label such as "Vol. 3 Chapter 105.5: Title".
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ChapterLabel {
+    pub number: Option<f64>,
+    /// The part after the decimal point of `number`, so "12.10" keeps its 10.
+    pub sub_chapter: Option<u32>,
+    pub volume: Option<u32>,
+    pub title: Option<String>,
+    /// Extras, omakes, specials and other side material.
+    pub extra: bool,
+}
+
+impl ChapterLabel {
+    pub fn parse(label: &str) -> Self {
+        // ASCII lowercasing keeps byte offsets valid for slicing `label`
+        let lower = label.to_ascii_lowercase();
+
+        let volume = number_after(&lower, &["volume", "vol.", "vol"]);
+        let chapter = number_after(&lower, &["chapter", "ch.", "ch"]).or_else(|| {
+            // Bare numbers, e.g. "105.5" or "Vol. 2 - 14"
+            let from = volume.as_ref().map_or(0, |v| v.end);
+            number_at(
+                &lower,
+                from + lower[from..].find(|c: char| c.is_ascii_digit())?,
+            )
+        });
+
+        let number = chapter
+            .as_ref()
+            .and_then(|c| c.raw.parse().ok())
+            .filter(|n: &f64| n.is_finite());
+        let sub_chapter = chapter
+            .as_ref()
+            .and_then(|c| c.raw.split_once('.')?.1.parse().ok());
+
+        let title_from = [&volume, &chapter]
+            .into_iter()
+            .flatten()
+            .map(|found| found.end)
+            .max()
+            .unwrap_or(0);
+
+        Self {
+            number,
+            sub_chapter,
+            volume: volume.and_then(|v| v.raw.split('.').next()?.parse().ok()),
+            title: title_after(label, title_from, chapter.is_some()),
+            extra: EXTRA_MARKERS.iter().any(|m| contains_word(&lower, m)),
+        }
+    }
+}
+
+/// A chapter number written on its own, as ComicInfo.xml's `Number` or an upload form has
+/// it, read the same way as in labels. Gives the number and its sub-chapter, or None for
+/// anything but a plain decimal such as "12a", "-1" or "NaN".
+pub fn parse_chapter_number(text: &str) -> Option<(f64, i64)> {
+    let text = text.trim();
+    if !text.starts_with(|c: char| c.is_ascii_digit())
+        || !text.chars().all(|c| c.is_ascii_digit() || c == '.')
+    {
+        return None;
+    }
+    let label = ChapterLabel::parse(text);
+    Some((label.number?, label.sub_chapter.map_or(0, i64::from)))
+}
+
+/// The sub-chapter a bare number implies, as it would be written: 12.5 is 5, 12 is 0.
+pub fn sub_chapter_of(number: f64) -> i64 {
+    number
+        .to_string()
+        .split_once('.')
+        .and_then(|(_, digits)| digits.parse().ok())
+        .unwrap_or(0)
+}
+
+/// The directory name a chapter is stored under, e.g. "chapter_12.5". The sub-chapter is
+/// spelled out where the number alone would lose it, so 12.10 doesn't share 12.1's.
+pub fn chapter_dir(number: f64, sub_chapter: i64) -> String {
+    if sub_chapter_of(number) == sub_chapter {
+        format!("chapter_{}", number)
+    } else {
+        format!("chapter_{}.{}", number.trunc(), sub_chapter)
+    }
+}
+
+struct Found {
+    raw: String,
+    end: usize,
+}
+
+// The number following the first of `keywords` that appears as a whole word.
+fn number_after(lower: &str, keywords: &[&str]) -> Option<Found> {
+    keywords.iter().find_map(|keyword| {
+        lower.match_indices(keyword).find_map(|(start, _)| {
+            let before = lower[..start].chars().next_back();
+            if before.is_some_and(|c| c.is_ascii_alphanumeric()) {
+                return None;
+            }
+            let after = start + keyword.len();
+            let gap = lower[after..]
+                .find(|c: char| !(c.is_whitespace() || c == '.' || c == '#'))
+                .unwrap_or(lower.len() - after);
+            number_at(lower, after + gap)
+        })
+    })
+}
+
+fn number_at(lower: &str, start: usize) -> Option<Found> {
+    let rest = &lower[start..];
+    let len = rest
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(rest.len());
+    let raw = rest[..len].trim_end_matches('.');
+    if raw.is_empty() || !raw.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(Found {
+        raw: raw.to_string(),
+        end: start + raw.len(),
+    })
+}
+
+// Whatever follows the numbers, minus separators. Labels without a number are all title.
+fn title_after(label: &str, from: usize, numbered: bool) -> Option<String> {
+    let rest = if numbered { &label[from..] } else { label };
+    let title = rest
+        .trim_start_matches(|c: char| c.is_whitespace() || matches!(c, ':' | '-' | '–' | '—'))
+        .trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+fn contains_word(lower: &str, word: &str) -> bool {
+    lower.match_indices(word).any(|(start, _)| {
+        let before = lower[..start].chars().next_back();
+        let after = lower[start + word.len()..].chars().next();
+        !before.is_some_and(|c| c.is_alphanumeric()) && !after.is_some_and(|c| c.is_alphanumeric())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_chapter() {
+        let label = ChapterLabel::parse("Chapter 12");
+
+        assert_eq!(label.number, Some(12.0));
+        assert_eq!(label.sub_chapter, None);
+        assert_eq!(label.volume, None);
+        assert_eq!(label.title, None);
+        assert!(!label.extra);
+    }
+
+    #[test]
+    fn test_sub_chapter() {
+        let label = ChapterLabel::parse("Chapter 105.5");
+        assert_eq!(label.number, Some(105.5));
+        assert_eq!(label.sub_chapter, Some(5));
+
+        let label = ChapterLabel::parse("Ch. 12.10");
+        assert_eq!(label.number, Some(12.1));
+        assert_eq!(label.sub_chapter, Some(10));
+    }
+
+    #[test]
+    fn test_parse_chapter_number() {
+        assert_eq!(parse_chapter_number("12"), Some((12.0, 0)));
+        assert_eq!(parse_chapter_number(" 12.10 "), Some((12.1, 10)));
+        for text in ["12a", "-1", "NaN", "inf", "1e3", "1.2.3", ""] {
+            assert_eq!(parse_chapter_number(text), None, "{text}");
+        }
+        // Too many digits for a float
+        assert_eq!(parse_chapter_number(&"9".repeat(400)), None);
+    }
+
+    #[test]
+    fn test_chapter_dir() {
+        assert_eq!(chapter_dir(3.0, 0), "chapter_3");
+        assert_eq!(chapter_dir(12.5, 5), "chapter_12.5");
+        assert_eq!(chapter_dir(12.1, 1), "chapter_12.1");
+        // A tenth part gets its own directory rather than sharing the first part's
+        assert_eq!(chapter_dir(12.1, 10), "chapter_12.10");
+        assert_eq!(sub_chapter_of(12.1), 1);
+        assert_eq!(sub_chapter_of(7.0), 0);
+    }
+
+    #[test]
+    fn test_volume_and_title() {
+        let label = ChapterLabel::parse("Vol. 3 Chapter 21: Dog & Chainsaw");
+
+        assert_eq!(label.volume, Some(3));
+        assert_eq!(label.number, Some(21.0));
+        assert_eq!(label.title.as_deref(), Some("Dog & Chainsaw"));
+
+        let label = ChapterLabel::parse("Volume 2 - 14 - The Return");
+        assert_eq!(label.volume, Some(2));
+        assert_eq!(label.number, Some(14.0));
+        assert_eq!(label.title.as_deref(), Some("The Return"));
+    }
+
+    #[test]
+    fn test_extras() {
+        let label = ChapterLabel::parse("Chapter 45.5 Omake");
+        assert!(label.extra);
+        assert_eq!(label.number, Some(45.5));
+        assert_eq!(label.title.as_deref(), Some("Omake"));
+
+        assert!(ChapterLabel::parse("Extra Chapter").extra);
+        assert!(ChapterLabel::parse("Chapter 3 (Special)").extra);
+        // "extraordinary" is not an extra
+        assert!(!ChapterLabel::parse("Chapter 4: Extraordinary").extra);
+    }
+
+    #[test]
+    fn test_unnumbered() {
+        let label = ChapterLabel::parse("Oneshot");
+        assert_eq!(label.number, None);
+        assert_eq!(label.title.as_deref(), Some("Oneshot"));
+
+        let label = ChapterLabel::parse("Unknown Chapter");
+        assert_eq!(label.number, None);
+        assert!(!label.extra);
+    }
+
+    #[test]
+    fn test_bare_number_and_trailing_dot() {
+        assert_eq!(ChapterLabel::parse("105.5").number, Some(105.5));
+        assert_eq!(ChapterLabel::parse("Chapter 7.").number, Some(7.0));
+        // "ch" inside a word is not a keyword
+        assert_eq!(ChapterLabel::parse("Match 3").number, Some(3.0));
+    }
+}
@@ -0,0 +1,360 @@
+use async_trait::async_trait;
+use reqwest::header;
+use serde::Deserialize;
+use url::Url;
+
+use super::{Chapter, MangaResult, Page, Source};
+use crate::fetcher::Fetcher;
+
+pub const API_BASE: &str = "https://api.mangadex.org";
+const SITE_BASE: &str = "https://mangadex.org";
+const UPLOADS_BASE: &str = "https://uploads.mangadex.org";
+
+// Largest page size the chapter feed accepts
+const FEED_LIMIT: usize = 500;
+
+/// MangaDex through its public JSON API rather than scraped HTML.
+pub struct MangaDex {
+    fetcher: Fetcher,
+    api_base: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Collection<T> {
+    data: Vec<T>,
+    total: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct MangaData {
+    id: String,
+    attributes: MangaAttributes,
+    #[serde(default)]
+    relationships: Vec<Relationship>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MangaAttributes {
+    title: std::collections::HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChapterData {
+    id: String,
+    attributes: ChapterAttributes,
+    #[serde(default)]
+    relationships: Vec<Relationship>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChapterAttributes {
+    volume: Option<String>,
+    chapter: Option<String>,
+    title: Option<String>,
+    translated_language: String,
+    // Chapters hosted off-site have no pages on the at-home servers
+    external_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Relationship {
+    #[serde(rename = "type")]
+    kind: String,
+    attributes: Option<RelationshipAttributes>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RelationshipAttributes {
+    name: Option<String>,
+    file_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AtHome {
+    base_url: String,
+    chapter: AtHomeChapter,
+}
+
+#[derive(Debug, Deserialize)]
+struct AtHomeChapter {
+    hash: String,
+    data: Vec<String>,
+}
+
+impl MangaDex {
+    pub fn new(api_base: &str, fetcher: Fetcher) -> Self {
+        Self {
+            fetcher,
+            api_base: api_base.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn default_headers() -> header::HeaderMap {
+        // MangaDex asks clients to identify themselves instead of spoofing a browser
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            "User-Agent",
+            header::HeaderValue::from_static(concat!("esfwee/", env!("CARGO_PKG_VERSION"))),
+        );
+        headers
+    }
+
+    async fn get<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> anyhow::Result<T> {
+        let url = Url::parse_with_params(&format!("{}{}", self.api_base, path), query)?;
+        self.fetcher.get_json(url, &Self::default_headers()).await
+    }
+}
+
+/// The UUID following `kind` in a mangadex.org URL, or the input itself when it's a bare id.
+fn uuid_after<'a>(url: &'a str, kind: &str) -> anyhow::Result<&'a str> {
+    if !url.contains('/') {
+        return Ok(url);
+    }
+    let mut segments = url.split('/');
+    segments
+        .find(|s| *s == kind)
+        .and_then(|_| segments.next())
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Invalid MangaDex {kind} URL"))
+}
+
+fn chapter_label(attributes: &ChapterAttributes) -> String {
+    let mut label = match &attributes.chapter {
+        Some(number) => format!("Chapter {number}"),
+        None => "Oneshot".to_string(),
+    };
+    if let Some(volume) = &attributes.volume {
+        label = format!("Vol. {volume} {label}");
+    }
+    match attributes.title.as_deref().filter(|t| !t.is_empty()) {
+        Some(title) => format!("{label}: {title}"),
+        None => label,
+    }
+}
+
+#[async_trait]
+impl Source for MangaDex {
+    fn id(&self) -> &'static str {
+        "mangadex"
+    }
+
+    fn headers(&self) -> header::HeaderMap {
+        Self::default_headers()
+    }
+
+    async fn search(&self, query: &str) -> anyhow::Result<Vec<MangaResult>> {
+        let results: Collection<MangaData> = self
+            .get(
+                "/manga",
+                &[
+                    ("title", query.to_string()),
+                    ("limit", "20".to_string()),
+                    ("includes[]", "cover_art".to_string()),
+                ],
+            )
+            .await?;
+
+        let manga = results
+            .data
+            .into_iter()
+            .filter_map(|m| {
+                let title = m
+                    .attributes
+                    .title
+                    .get("en")
+                    .or_else(|| m.attributes.title.values().next())?
+                    .clone();
+                let thumbnail = m
+                    .relationships
+                    .iter()
+                    .filter(|r| r.kind == "cover_art")
+                    .find_map(|r| r.attributes.as_ref()?.file_name.as_ref())
+                    .map(|file| format!("{UPLOADS_BASE}/covers/{}/{file}.256.jpg", m.id));
+
+                Some(MangaResult {
+                    title,
+                    url: format!("{SITE_BASE}/title/{}", m.id),
+                    thumbnail,
+                })
+            })
+            .collect();
+
+        Ok(manga)
+    }
+
+    async fn chapters(
+        &self,
+        manga_url: &str,
+        language: Option<&str>,
+    ) -> anyhow::Result<Vec<Chapter>> {
+        let manga_id = uuid_after(manga_url, "title")?;
+        let path = format!("/manga/{manga_id}/feed");
+
+        let mut chapters = Vec::new();
+        let mut offset = 0;
+        loop {
+            let mut query = vec![
+                ("limit", FEED_LIMIT.to_string()),
+                ("offset", offset.to_string()),
+                ("order[chapter]", "asc".to_string()),
+                ("includes[]", "scanlation_group".to_string()),
+            ];
+            if let Some(language) = language {
+                query.push(("translatedLanguage[]", language.to_string()));
+            }
+
+            let feed: Collection<ChapterData> = self.get(&path, &query).await?;
+            let fetched = feed.data.len();
+
+            chapters.extend(
+                feed.data
+                    .into_iter()
+                    .filter(|c| c.attributes.external_url.is_none())
+                    .map(|c| Chapter {
+                        language: Some(c.attributes.translated_language.clone()),
+                        group: c
+                            .relationships
+                            .iter()
+                            .filter(|r| r.kind == "scanlation_group")
+                            .find_map(|r| r.attributes.as_ref()?.name.clone()),
+                        ..Chapter::new(
+                            chapter_label(&c.attributes),
+                            format!("{SITE_BASE}/chapter/{}", c.id),
+                        )
+                    }),
+            );
+
+            offset += fetched;
+            if fetched == 0 || offset >= feed.total {
+                break;
+            }
+        }
+
+        Ok(chapters)
+    }
+
+    async fn pages(&self, chapter_url: &str) -> anyhow::Result<Vec<Page>> {
+        let chapter_id = uuid_after(chapter_url, "chapter")?;
+        let at_home: AtHome = self
+            .get(&format!("/at-home/server/{chapter_id}"), &[])
+            .await?;
+
+        let pages = at_home
+            .chapter
+            .data
+            .iter()
+            .map(|file| Page {
+                url: format!("{}/data/{}/{file}", at_home.base_url, at_home.chapter.hash),
+            })
+            .collect();
+
+        Ok(pages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrrrr::serve_fixtures;
+    use axum::extract::Query;
+    use axum::{Router, routing::get};
+    use std::collections::HashMap;
+
+    const MANGA_ID: &str = "a77742b1-befd-49a4-bff5-1ad4e6b0ef7b";
+    const CHAPTER_ID: &str = "3f1e7a2d-9c4b-4f65-8a9e-0d1c2b3a4f5e";
+
+    async fn mock_api() -> MangaDex {
+        let router = Router::new()
+            .route(
+                "/manga",
+                get(|| async { include_str!("fixtures/mangadex/search.json") }),
+            )
+            .route(
+                "/manga/{id}/feed",
+                get(|Query(query): Query<HashMap<String, String>>| async move {
+                    match query.get("translatedLanguage[]").map(String::as_str) {
+                        Some("en") => include_str!("fixtures/mangadex/feed_en.json"),
+                        _ => include_str!("fixtures/mangadex/feed.json"),
+                    }
+                }),
+            )
+            .route(
+                "/at-home/server/{id}",
+                get(|| async { include_str!("fixtures/mangadex/at_home.json") }),
+            );
+
+        MangaDex::new(&serve_fixtures(router).await, Fetcher::default())
+    }
+
+    #[test]
+    fn test_uuid_after() {
+        let url = format!("https://mangadex.org/title/{MANGA_ID}/chainsaw-man");
+        assert_eq!(uuid_after(&url, "title").unwrap(), MANGA_ID);
+        assert_eq!(uuid_after(MANGA_ID, "title").unwrap(), MANGA_ID);
+        assert!(uuid_after("https://mangadex.org/titles", "title").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let results = mock_api().await.search("chainsaw").await.unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].title, "Chainsaw Man");
+        assert_eq!(results[0].url, format!("{SITE_BASE}/title/{MANGA_ID}"));
+        assert_eq!(
+            results[0].thumbnail.as_deref(),
+            Some(
+                "https://uploads.mangadex.org/covers/a77742b1-befd-49a4-bff5-1ad4e6b0ef7b/cover.jpg.256.jpg"
+            )
+        );
+        // Falls back to any title when there's no English one
+        assert_eq!(results[1].title, "チェンソーマン 短編集");
+        assert!(results[1].thumbnail.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_chapters_all_languages() {
+        let chapters = mock_api().await.chapters(MANGA_ID, None).await.unwrap();
+
+        // The externally hosted chapter is dropped
+        assert_eq!(chapters.len(), 3);
+        assert_eq!(chapters[0].chapter, "Vol. 1 Chapter 1: Dog & Chainsaw");
+        assert_eq!(chapters[0].label.volume, Some(1));
+        assert_eq!(chapters[0].label.number, Some(1.0));
+        assert_eq!(chapters[0].label.title.as_deref(), Some("Dog & Chainsaw"));
+        assert_eq!(chapters[0].language.as_deref(), Some("en"));
+        assert_eq!(chapters[0].group.as_deref(), Some("Test Scans"));
+        assert_eq!(chapters[1].language.as_deref(), Some("fr"));
+        assert_eq!(chapters[2].chapter, "Oneshot");
+        assert_eq!(chapters[2].label.number, None);
+        assert!(chapters[2].group.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_chapters_filtered_by_language() {
+        let url = format!("https://mangadex.org/title/{MANGA_ID}");
+        let chapters = mock_api().await.chapters(&url, Some("en")).await.unwrap();
+
+        assert_eq!(chapters.len(), 1);
+        assert_eq!(chapters[0].url, format!("{SITE_BASE}/chapter/{CHAPTER_ID}"));
+    }
+
+    #[tokio::test]
+    async fn test_pages() {
+        let url = format!("https://mangadex.org/chapter/{CHAPTER_ID}");
+        let pages = mock_api().await.pages(&url).await.unwrap();
+
+        assert_eq!(pages.len(), 3);
+        assert_eq!(
+            pages[0].url,
+            "https://cdn.example.org/data/2c9f2a9f7e8d4d1b/1-page.png"
+        );
+    }
+}
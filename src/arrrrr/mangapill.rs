@@ -0,0 +1,275 @@
+use async_trait::async_trait;
+use reqwest::header;
+use scraper::{Html, Selector};
+use url::Url;
+
+use super::{Chapter, MangaResult, Page, Source};
+use crate::fetcher::Fetcher;
+
+pub const MANGA_BASE: &str = "https://mangapill.com";
+
+pub struct MangaPill {
+    fetcher: Fetcher,
+    base: Url,
+}
+
+impl MangaPill {
+    /// `base` is the site root every scraped path is resolved against.
+    pub fn new(base: &str, fetcher: Fetcher) -> Self {
+        Self {
+            fetcher,
+            base: Url::parse(base).expect("invalid mangapill base URL"),
+        }
+    }
+
+    fn default_headers() -> header::HeaderMap {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            "Referer",
+            header::HeaderValue::from_static("https://mangapill.com/"),
+        );
+        headers.insert("User-Agent", header::HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/129.0.0.0 Safari/537.36"));
+        headers
+    }
+}
+
+#[async_trait]
+impl Source for MangaPill {
+    fn id(&self) -> &'static str {
+        "mangapill"
+    }
+
+    fn headers(&self) -> header::HeaderMap {
+        Self::default_headers()
+    }
+
+    async fn search(&self, query: &str) -> anyhow::Result<Vec<MangaResult>> {
+        let url = self
+            .base
+            .join(&format!("/search?page=1&q={}", urlencoding::encode(query)))?;
+
+        let resp = self.fetcher.get_text(url, &self.headers()).await?;
+        let document = Html::parse_document(&resp);
+
+        let item_sel = Selector::parse(".grid > div:not([class])").unwrap();
+        let a_sel = Selector::parse("a").unwrap();
+        let title_sel = Selector::parse("div[class] > a").unwrap();
+        let img_sel = Selector::parse("img").unwrap();
+
+        let mut results = Vec::new();
+
+        for el in document.select(&item_sel) {
+            let Some(anchor) = el.select(&a_sel).next() else {
+                continue;
+            };
+            let Some(rel_url) = anchor.value().attr("href") else {
+                continue;
+            };
+            let full_url = self.base.join(rel_url)?.to_string();
+
+            let title = el
+                .select(&title_sel)
+                .next()
+                .map(|t| t.text().collect::<String>().trim().to_owned())
+                .unwrap_or_default();
+
+            if title.is_empty() {
+                continue;
+            }
+
+            let thumbnail = el
+                .select(&img_sel)
+                .next()
+                .and_then(|i| i.value().attr("data-src"))
+                .map(str::to_owned);
+
+            results.push(MangaResult {
+                title,
+                url: full_url,
+                thumbnail,
+            });
+        }
+
+        Ok(results)
+    }
+
+    // Mangapill only carries English releases, so there's nothing to filter on
+    async fn chapters(
+        &self,
+        manga_url: &str,
+        _language: Option<&str>,
+    ) -> anyhow::Result<Vec<Chapter>> {
+        if !manga_url.starts_with("http") {
+            return Err(anyhow::anyhow!("Invalid manga URL"));
+        }
+
+        let path = Url::parse(manga_url)?.path().to_string();
+        let resp = self
+            .fetcher
+            .get_text(self.base.join(&path)?, &self.headers())
+            .await?;
+        let document = Html::parse_document(&resp);
+
+        let chapter_sel = Selector::parse("#chapters > div > a").unwrap();
+        let mut chapters = Vec::new();
+
+        for el in document.select(&chapter_sel) {
+            let Some(rel_url) = el.value().attr("href") else {
+                continue;
+            };
+            let chapter_url = self.base.join(rel_url)?.to_string();
+            let chapter_title = el.text().collect::<String>().trim().to_owned();
+            let chapter_title = if chapter_title.is_empty() {
+                "Unknown Chapter".to_string()
+            } else {
+                chapter_title
+            };
+
+            chapters.push(Chapter::new(chapter_title, chapter_url));
+        }
+
+        Ok(chapters)
+    }
+
+    async fn pages(&self, chapter_url: &str) -> anyhow::Result<Vec<Page>> {
+        if !chapter_url.starts_with("http") {
+            return Err(anyhow::anyhow!("Invalid chapter URL"));
+        }
+
+        let path = Url::parse(chapter_url)?.path().to_string();
+        let resp = self
+            .fetcher
+            .get_text(self.base.join(&path)?, &self.headers())
+            .await?;
+        let document = Html::parse_document(&resp);
+
+        let img_sel = Selector::parse("picture img").unwrap();
+        let pages: Vec<Page> = document
+            .select(&img_sel)
+            .filter_map(|el| el.value().attr("data-src"))
+            .map(|url| Page {
+                url: url.to_string(),
+            })
+            .collect();
+
+        Ok(pages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrrrr::serve_fixtures;
+    use axum::extract::Query;
+    use axum::http::StatusCode;
+    use axum::response::Html as HtmlResponse;
+    use axum::{Router, routing::get};
+    use std::collections::HashMap;
+
+    async fn stub_site() -> (MangaPill, String) {
+        let router = Router::new()
+            .route(
+                "/search",
+                get(|Query(query): Query<HashMap<String, String>>| async move {
+                    match query.get("q").map(String::as_str) {
+                        Some("chainsaw man") => {
+                            Ok(HtmlResponse(include_str!("fixtures/mangapill/search.html")))
+                        }
+                        _ => Err(StatusCode::BAD_REQUEST),
+                    }
+                }),
+            )
+            .route(
+                "/manga/{id}/{slug}",
+                get(|| async { HtmlResponse(include_str!("fixtures/mangapill/manga.html")) }),
+            )
+            .route(
+                "/chapters/{id}/{slug}",
+                get(|| async { HtmlResponse(include_str!("fixtures/mangapill/chapter.html")) }),
+            );
+
+        let base = serve_fixtures(router).await;
+        (MangaPill::new(&base, Fetcher::default()), base)
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let (source, base) = stub_site().await;
+
+        let results = source.search("chainsaw man").await.unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].title, "Chainsaw Man");
+        assert_eq!(results[0].url, format!("{base}/manga/2/chainsaw-man"));
+        assert_eq!(
+            results[0].thumbnail.as_deref(),
+            Some("https://cdn.readdetectiveconan.com/file/mangapill/i/2.jpeg")
+        );
+        assert_eq!(results[1].title, "Chainsaw Man Buddy Stories");
+        assert!(results[1].thumbnail.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_chapters() {
+        let (source, base) = stub_site().await;
+
+        // Only the path is kept, so links to the real site resolve against the stub
+        let chapters = source
+            .chapters("https://mangapill.com/manga/2/chainsaw-man", None)
+            .await
+            .unwrap();
+
+        let labels: Vec<&str> = chapters.iter().map(|c| c.chapter.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "Chapter 106",
+                "Chapter 105.5",
+                "Chapter 1",
+                "Unknown Chapter"
+            ]
+        );
+        assert_eq!(
+            chapters[1].url,
+            format!("{base}/chapters/2-10105500/chainsaw-man-chapter-105.5")
+        );
+        assert_eq!(chapters[1].label.number, Some(105.5));
+        assert_eq!(chapters[1].label.sub_chapter, Some(5));
+        assert_eq!(chapters[3].label.number, None);
+        assert!(chapters[0].language.is_none() && chapters[0].group.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pages() {
+        let (source, base) = stub_site().await;
+
+        let pages = source
+            .pages(&format!(
+                "{base}/chapters/2-10105500/chainsaw-man-chapter-105.5"
+            ))
+            .await
+            .unwrap();
+
+        let urls: Vec<&str> = pages.iter().map(|p| p.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://cdn.readdetectiveconan.com/file/mangap/2/10105500/1.jpeg",
+                "https://cdn.readdetectiveconan.com/file/mangap/2/10105500/2.jpeg",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_relative_urls() {
+        let (source, _) = stub_site().await;
+
+        assert!(
+            source
+                .chapters("/manga/2/chainsaw-man", None)
+                .await
+                .is_err()
+        );
+        assert!(source.pages("/chapters/2-10105500/x").await.is_err());
+    }
+}
@@ -1,12 +1,22 @@
-use reqwest::{Client, header};
-use scraper::{Html, Selector};
-use serde::Serialize;
-use std::time::Duration;
-use url::Url;
+pub mod label;
+pub mod mangadex;
+pub mod mangapill;
 
-const MANGA_BASE: &str = "https://mangapill.com";
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
 
-#[derive(Debug, Serialize)]
+use crate::fetcher::Fetcher;
+use label::ChapterLabel;
+use mangadex::MangaDex;
+use mangapill::MangaPill;
+
+/// Source used when a request doesn't name one.
+pub const DEFAULT_SOURCE: &str = "mangapill";
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MangaResult {
     pub title: String,
     pub url: String,
@@ -17,6 +27,14 @@ pub struct MangaResult {
 pub struct Chapter {
     pub chapter: String,
     pub url: String,
+    /// Number, volume and title parsed out of `chapter`.
+    #[serde(flatten)]
+    pub label: ChapterLabel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Scanlation group, for sources that credit one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -24,134 +42,93 @@ pub struct Page {
     pub url: String,
 }
 
-fn create_client() -> Client {
-    Client::builder()
-        .timeout(Duration::from_secs(10))
-        .default_headers({
-            let mut headers = header::HeaderMap::new();
-            headers.insert("Referer", header::HeaderValue::from_static("https://mangapill.com/"));
-            headers.insert("User-Agent", header::HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/129.0.0.0 Safari/537.36"));
-            headers
-        })
-        .build()
-        .unwrap()
+impl Chapter {
+    pub fn new(chapter: String, url: String) -> Self {
+        Self {
+            label: ChapterLabel::parse(&chapter),
+            chapter,
+            url,
+            language: None,
+            group: None,
+        }
+    }
 }
 
-pub async fn search_manga_pill(query: &str) -> anyhow::Result<Vec<MangaResult>> {
-    let client = create_client();
-    let url = format!(
-        "{}/search?page=1&q={}",
-        MANGA_BASE,
-        urlencoding::encode(query)
-    );
-
-    let resp = client.get(&url).send().await?.text().await?;
-    let document = Html::parse_document(&resp);
-
-    let item_sel = Selector::parse(".grid > div:not([class])").unwrap();
-    let a_sel = Selector::parse("a").unwrap();
-    let title_sel = Selector::parse("div[class] > a").unwrap();
-    let img_sel = Selector::parse("img").unwrap();
-
-    let mut results = Vec::new();
-
-    for el in document.select(&item_sel) {
-        let Some(anchor) = el.select(&a_sel).next() else {
-            continue;
-        };
-        let Some(rel_url) = anchor.value().attr("href") else {
-            continue;
-        };
-        let full_url = Url::parse(MANGA_BASE)?.join(rel_url)?.to_string();
-
-        let title = el
-            .select(&title_sel)
-            .next()
-            .map(|t| t.text().collect::<String>().trim().to_owned())
-            .unwrap_or_default();
-
-        if title.is_empty() {
-            continue;
-        }
+/// A site chapters can be scraped from.
+#[async_trait]
+pub trait Source: Send + Sync {
+    /// Name used for the `source` request parameter and stored on download jobs.
+    fn id(&self) -> &'static str;
 
-        let thumbnail = el
-            .select(&img_sel)
-            .next()
-            .and_then(|i| i.value().attr("data-src"))
-            .map(str::to_owned);
-
-        results.push(MangaResult {
-            title,
-            url: full_url,
-            thumbnail,
-        });
-    }
+    /// Headers the site expects on page image requests (referer, user agent...).
+    fn headers(&self) -> HeaderMap;
+
+    async fn search(&self, query: &str) -> anyhow::Result<Vec<MangaResult>>;
 
-    Ok(results)
+    /// Chapters of a series, narrowed to one language code ("en") when the source has several.
+    async fn chapters(
+        &self,
+        manga_url: &str,
+        language: Option<&str>,
+    ) -> anyhow::Result<Vec<Chapter>>;
+
+    async fn pages(&self, chapter_url: &str) -> anyhow::Result<Vec<Page>>;
 }
 
-pub async fn get_manga_pill_chapters(manga_url: &str) -> anyhow::Result<Vec<Chapter>> {
-    if !manga_url.starts_with("http") {
-        return Err(anyhow::anyhow!("Invalid manga URL"));
+/// Every registered [`Source`], keyed by id.
+#[derive(Clone)]
+pub struct Sources(Arc<HashMap<&'static str, Arc<dyn Source>>>);
+
+impl Sources {
+    pub fn new(sources: Vec<Arc<dyn Source>>) -> Self {
+        Self(Arc::new(sources.into_iter().map(|s| (s.id(), s)).collect()))
     }
 
-    let client = create_client();
-    let path = Url::parse(manga_url)?.path().to_string();
-    let resp = client
-        .get(format!("{}{}", MANGA_BASE, path))
-        .send()
-        .await?
-        .text()
-        .await?;
-    let document = Html::parse_document(&resp);
-
-    let chapter_sel = Selector::parse("#chapters > div > a").unwrap();
-    let mut chapters = Vec::new();
-
-    for el in document.select(&chapter_sel) {
-        let Some(rel_url) = el.value().attr("href") else {
-            continue;
-        };
-        let chapter_url = Url::parse(MANGA_BASE)?.join(rel_url)?.to_string();
-        let chapter_title = el.text().collect::<String>().trim().to_owned();
-        let chapter_title = if chapter_title.is_empty() {
-            "Unknown Chapter".to_string()
-        } else {
-            chapter_title
-        };
-
-        chapters.push(Chapter {
-            chapter: chapter_title,
-            url: chapter_url,
-        });
+    /// Every built-in source, all sharing `fetcher`.
+    pub fn builtin(fetcher: &Fetcher) -> Self {
+        Self::new(vec![
+            Arc::new(MangaPill::new(mangapill::MANGA_BASE, fetcher.clone())),
+            Arc::new(MangaDex::new(mangadex::API_BASE, fetcher.clone())),
+        ])
     }
 
-    Ok(chapters)
-}
+    pub fn get(&self, id: &str) -> anyhow::Result<Arc<dyn Source>> {
+        self.0
+            .get(id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Unknown source: {id}"))
+    }
 
-pub async fn get_manga_pill_pages(chapter_url: &str) -> anyhow::Result<Vec<Page>> {
-    if !chapter_url.starts_with("http") {
-        return Err(anyhow::anyhow!("Invalid chapter URL"));
+    pub fn ids(&self) -> Vec<&'static str> {
+        let mut ids: Vec<_> = self.0.keys().copied().collect();
+        ids.sort();
+        ids
     }
+}
+
+pub fn default_source() -> String {
+    DEFAULT_SOURCE.to_string()
+}
 
-    let client = create_client();
-    let path = Url::parse(chapter_url)?.path().to_string();
-    let resp = client
-        .get(format!("{}{}", MANGA_BASE, path))
-        .send()
-        .await?
-        .text()
-        .await?;
-    let document = Html::parse_document(&resp);
-
-    let img_sel = Selector::parse("picture img").unwrap();
-    let pages: Vec<Page> = document
-        .select(&img_sel)
-        .filter_map(|el| el.value().attr("data-src"))
-        .map(|url| Page {
-            url: url.to_string(),
-        })
-        .collect();
-
-    Ok(pages)
+/// Serves `router` on a random local port and returns its base URL.
+#[cfg(test)]
+pub(crate) async fn serve_fixtures(router: axum::Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{addr}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sources_lookup() {
+        let sources = Sources::builtin(&Fetcher::default());
+
+        assert_eq!(sources.get(DEFAULT_SOURCE).unwrap().id(), DEFAULT_SOURCE);
+        assert!(sources.get("nope").is_err());
+        assert_eq!(sources.ids(), vec!["mangadex", "mangapill"]);
+    }
 }
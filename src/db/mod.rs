@@ -1,16 +1,30 @@
-use sqlx::{Pool, Sqlite, migrate::MigrateDatabase, sqlite::SqlitePoolOptions};
+use anyhow::anyhow;
+use serde::Serialize;
+use sqlx::migrate::{MigrateDatabase, Migrator};
+use sqlx::{Pool, Sqlite, sqlite::SqlitePoolOptions};
 use std::{env, path::Path};
 
+/// Everything under `migrations/`, compiled into the binary.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+#[derive(Debug, Serialize)]
+pub struct SchemaStatus {
+    /// Newest migration applied to the database, if any.
+    pub version: Option<i64>,
+    /// Newest migration this binary knows about.
+    pub latest: i64,
+    pub up_to_date: bool,
+}
+
 pub async fn connect_db() -> Pool<Sqlite> {
     let db_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
     if let Some(path_str) = db_url
         .strip_prefix("sqlite://")
         .or_else(|| db_url.strip_prefix("sqlite:"))
+        && let Some(parent) = Path::new(path_str).parent()
     {
-        if let Some(parent) = Path::new(path_str).parent() {
-            std::fs::create_dir_all(parent).expect("Failed to create database directory");
-        }
+        std::fs::create_dir_all(parent).expect("Failed to create database directory");
     }
 
     if !Sqlite::database_exists(&db_url).await.unwrap_or(false) {
@@ -25,5 +39,163 @@ pub async fn connect_db() -> Pool<Sqlite> {
         .await
         .expect("Failed to create pool.");
 
+    migrate(&pool).await.expect("Failed to migrate database");
+
     pool
 }
+
+/// Applies pending migrations, refusing databases written by a newer build.
+pub async fn migrate(pool: &Pool<Sqlite>) -> anyhow::Result<()> {
+    let status = schema_status(pool).await?;
+    if let Some(version) = status.version
+        && version > status.latest
+    {
+        return Err(anyhow!(
+            "Database schema version {version} is newer than this build supports ({}), upgrade esfwee",
+            status.latest
+        ));
+    }
+
+    MIGRATOR.run(pool).await?;
+    Ok(())
+}
+
+pub async fn schema_status(pool: &Pool<Sqlite>) -> anyhow::Result<SchemaStatus> {
+    let latest = MIGRATOR.iter().map(|m| m.version).max().unwrap_or(0);
+
+    // The bookkeeping table only exists once sqlx has migrated this database
+    let tracked: Option<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'",
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let version = match tracked {
+        Some(_) => {
+            sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success = 1")
+                .fetch_one(pool)
+                .await?
+        }
+        None => None,
+    };
+
+    Ok(SchemaStatus {
+        version,
+        latest,
+        up_to_date: version == Some(latest),
+    })
+}
+
+#[cfg(test)]
+pub async fn test_pool() -> Pool<Sqlite> {
+    // A single connection, otherwise every pooled connection gets its own in-memory database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("Failed to create test pool.");
+    migrate(&pool).await.expect("Failed to run migrations");
+    pool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_migrate_fresh_database() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        let before = schema_status(&pool).await.unwrap();
+        assert_eq!(before.version, None);
+        assert!(!before.up_to_date);
+
+        migrate(&pool).await.unwrap();
+
+        let after = schema_status(&pool).await.unwrap();
+        assert_eq!(after.version, Some(after.latest));
+        assert!(after.up_to_date);
+        sqlx::query("SELECT id, anilist_id FROM manga")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_series_ids_keep_existing_rows() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let (before, after): (Vec<_>, Vec<_>) =
+            MIGRATOR.iter().partition(|m| m.version < 20251206090000);
+        for migration in before {
+            sqlx::raw_sql(&migration.sql).execute(&pool).await.unwrap();
+        }
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO manga (anilist_id, title, storage_path) VALUES (30013, 'One Piece', 'data/manga/30013');
+            INSERT INTO chapters (id, anilist_id, chapter_number, page_count, storage_path)
+                VALUES (7, 30013, 1, 3, 'data/manga/30013/chapter_1');
+            INSERT INTO reading_progress (anilist_id, chapter_id, page, completed) VALUES (30013, 7, 2, 0);
+            INSERT INTO anilist_sync (anilist_id) VALUES (30013);
+            INSERT INTO download_jobs (anilist_id, chapter_url, chapter_number, status) VALUES
+                (105778, 'https://mangapill.com/chapters/1', 1, 'queued'),
+                (44, 'https://mangapill.com/chapters/2', 1, 'completed');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        for migration in after {
+            sqlx::raw_sql(&migration.sql).execute(&pool).await.unwrap();
+        }
+
+        let manga: Vec<(i64, Option<i64>)> =
+            sqlx::query_as("SELECT id, anilist_id FROM manga ORDER BY id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        // The queued download has a series to land in; finished ones aren't revived
+        assert_eq!(manga, vec![(30013, Some(30013)), (105778, Some(105778))]);
+        for table in ["chapters", "reading_progress", "anilist_sync"] {
+            let series: i64 = sqlx::query_scalar(&format!("SELECT manga_id FROM {table}"))
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(series, 30013, "{table}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_migrate_is_idempotent() {
+        let pool = test_pool().await;
+        migrate(&pool).await.unwrap();
+        assert!(schema_status(&pool).await.unwrap().up_to_date);
+    }
+
+    #[tokio::test]
+    async fn test_refuses_newer_database() {
+        let pool = test_pool().await;
+        let latest = schema_status(&pool).await.unwrap().latest;
+        sqlx::query(
+            r#"
+            INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+            VALUES (?, 'from the future', 1, x'00', 0)
+            "#,
+        )
+        .bind(latest + 1)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let err = migrate(&pool).await.unwrap_err();
+        assert!(err.to_string().contains("newer than this build"));
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Client, Response, StatusCode};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use url::Url;
+
+// Never wait longer than this between two attempts, whatever Retry-After says
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+pub struct FetcherConfig {
+    /// Minimum gap between two requests to the same host.
+    pub host_interval: Duration,
+    /// Attempts after the first one for 429s, 5xxs and connection errors.
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for every one after.
+    pub backoff: Duration,
+    /// Page images fetched at once by a single download job.
+    pub page_concurrency: usize,
+    pub timeout: Duration,
+}
+
+impl Default for FetcherConfig {
+    fn default() -> Self {
+        Self {
+            host_interval: Duration::from_millis(250),
+            max_retries: 4,
+            backoff: Duration::from_millis(500),
+            page_concurrency: 4,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl FetcherConfig {
+    /// Defaults overridden by `HTTP_HOST_INTERVAL_MS`, `HTTP_MAX_RETRIES`,
+    /// `HTTP_BACKOFF_MS` and `PAGE_CONCURRENCY`.
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok()?.parse().ok()
+        }
+
+        let default = Self::default();
+        Self {
+            host_interval: var("HTTP_HOST_INTERVAL_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(default.host_interval),
+            max_retries: var("HTTP_MAX_RETRIES").unwrap_or(default.max_retries),
+            backoff: var("HTTP_BACKOFF_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(default.backoff),
+            page_concurrency: var("PAGE_CONCURRENCY").unwrap_or(default.page_concurrency),
+            timeout: default.timeout,
+        }
+    }
+}
+
+/// The one HTTP client every outbound scraper and image request goes through.
+/// Spaces out requests per host and retries transient failures with exponential backoff.
+#[derive(Clone)]
+pub struct Fetcher {
+    client: Client,
+    config: Arc<FetcherConfig>,
+    // Earliest time the next request to each host may start
+    next_slot: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl Fetcher {
+    pub fn new(config: FetcherConfig) -> Self {
+        let client = Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .expect("failed to build HTTP client");
+        Self {
+            client,
+            config: Arc::new(config),
+            next_slot: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn page_concurrency(&self) -> usize {
+        self.config.page_concurrency.max(1)
+    }
+
+    /// GETs `url`, failing once retries are exhausted or on any other non-success status.
+    pub async fn get(&self, url: Url, headers: &HeaderMap) -> anyhow::Result<Response> {
+        let mut attempt = 0;
+        loop {
+            self.wait_for_slot(&url).await;
+
+            let result = self
+                .client
+                .get(url.clone())
+                .headers(headers.clone())
+                .send()
+                .await;
+
+            let retry_in = match &result {
+                Ok(response) if is_transient(response.status()) => {
+                    Some(retry_after(response).unwrap_or_else(|| self.backoff(attempt)))
+                }
+                Err(e) if e.is_timeout() || e.is_connect() || e.is_request() => {
+                    Some(self.backoff(attempt))
+                }
+                _ => None,
+            };
+
+            match retry_in {
+                Some(delay) if attempt < self.config.max_retries => {
+                    attempt += 1;
+                    tokio::time::sleep(delay.min(MAX_BACKOFF)).await;
+                }
+                _ => return Ok(result?.error_for_status()?),
+            }
+        }
+    }
+
+    pub async fn get_text(&self, url: Url, headers: &HeaderMap) -> anyhow::Result<String> {
+        Ok(self.get(url, headers).await?.text().await?)
+    }
+
+    pub async fn get_json<T: for<'de> serde::Deserialize<'de>>(
+        &self,
+        url: Url,
+        headers: &HeaderMap,
+    ) -> anyhow::Result<T> {
+        Ok(self.get(url, headers).await?.json().await?)
+    }
+
+    async fn wait_for_slot(&self, url: &Url) {
+        let Some(host) = url.host_str() else {
+            return;
+        };
+
+        let start = {
+            let mut slots = self.next_slot.lock().await;
+            let now = Instant::now();
+            let start = slots.get(host).copied().unwrap_or(now).max(now);
+            slots.insert(host.to_string(), start + self.config.host_interval);
+            start
+        };
+        tokio::time::sleep_until(start).await;
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.config
+            .backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+    }
+}
+
+impl Default for Fetcher {
+    fn default() -> Self {
+        Self::new(FetcherConfig::default())
+    }
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+// Only the delay-seconds form; HTTP dates fall back to our own backoff
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    seconds.trim().parse().ok().map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrrrr::serve_fixtures;
+    use axum::http::StatusCode as AxumStatus;
+    use axum::{Router, routing::get};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn quick_config() -> FetcherConfig {
+        FetcherConfig {
+            host_interval: Duration::ZERO,
+            max_retries: 3,
+            backoff: Duration::from_millis(1),
+            ..FetcherConfig::default()
+        }
+    }
+
+    // Answers with `failures` 503s before succeeding, counting every hit
+    async fn flaky(failures: usize, status: AxumStatus) -> (Url, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let router = Router::new().route(
+            "/page",
+            get(move || {
+                let counter = counter.clone();
+                async move {
+                    if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                        (status, "try again")
+                    } else {
+                        (AxumStatus::OK, "page")
+                    }
+                }
+            }),
+        );
+        let base = serve_fixtures(router).await;
+        (Url::parse(&format!("{base}/page")).unwrap(), hits)
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors() {
+        let (url, hits) = flaky(2, AxumStatus::SERVICE_UNAVAILABLE).await;
+        let fetcher = Fetcher::new(quick_config());
+
+        let body = fetcher.get_text(url, &HeaderMap::new()).await.unwrap();
+
+        assert_eq!(body, "page");
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retries_rate_limits() {
+        let (url, hits) = flaky(1, AxumStatus::TOO_MANY_REQUESTS).await;
+        let fetcher = Fetcher::new(quick_config());
+
+        assert!(fetcher.get(url, &HeaderMap::new()).await.is_ok());
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let (url, hits) = flaky(10, AxumStatus::BAD_GATEWAY).await;
+        let fetcher = Fetcher::new(quick_config());
+
+        assert!(fetcher.get(url, &HeaderMap::new()).await.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_client_errors() {
+        let (url, hits) = flaky(10, AxumStatus::NOT_FOUND).await;
+        let fetcher = Fetcher::new(quick_config());
+
+        assert!(fetcher.get(url, &HeaderMap::new()).await.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_spaces_requests_per_host() {
+        let (url, _) = flaky(0, AxumStatus::OK).await;
+        let fetcher = Fetcher::new(FetcherConfig {
+            host_interval: Duration::from_millis(100),
+            ..quick_config()
+        });
+
+        let started = Instant::now();
+        for _ in 0..3 {
+            fetcher.get(url.clone(), &HeaderMap::new()).await.unwrap();
+        }
+
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,305 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::anyhow;
+use zip::ZipArchive;
+
+use super::epub::extract_epub;
+use super::pdf::extract_pdf;
+use super::rar::RarArchive;
+use super::{ExtractLimits, Extracted, MAX_COMIC_INFO_SIZE, Pages, is_comic_info};
+
+/// The container a chapter was uploaded in, told apart by its magic bytes rather than
+/// its extension, which is often wrong on older releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// CBZ
+    Zip,
+    /// CBR
+    Rar,
+    /// CB7
+    SevenZip,
+    /// CBT
+    Tar,
+    Pdf,
+    /// A zip that declares itself an EPUB in its leading `mimetype` entry
+    Epub,
+}
+
+impl ArchiveFormat {
+    pub fn detect(path: &Path) -> anyhow::Result<Self> {
+        // The tar magic sits furthest in, at 257
+        let mut header = Vec::with_capacity(262);
+        File::open(path)?.take(262).read_to_end(&mut header)?;
+
+        if header.starts_with(b"PK\x03\x04") && is_epub(&header) {
+            Ok(Self::Epub)
+        } else if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Ok(Self::Zip)
+        } else if header.starts_with(b"Rar!\x1a\x07") {
+            Ok(Self::Rar)
+        } else if header.starts_with(b"7z\xbc\xaf\x27\x1c") {
+            Ok(Self::SevenZip)
+        } else if header.get(257..262) == Some(b"ustar") {
+            Ok(Self::Tar)
+        } else if header.starts_with(b"%PDF-") {
+            Ok(Self::Pdf)
+        } else {
+            Err(anyhow!("Unsupported archive format"))
+        }
+    }
+}
+
+// EPUBs must start with an uncompressed `mimetype` entry naming their type
+fn is_epub(header: &[u8]) -> bool {
+    let field = |at: usize| {
+        header
+            .get(at..at + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    };
+    let (Some(name_len), Some(extra_len)) = (field(26), field(28)) else {
+        return false;
+    };
+    let contents = 30 + name_len as usize + extra_len as usize;
+    header.get(30..38) == Some(b"mimetype")
+        && header.get(contents..contents + 20) == Some(b"application/epub+zip")
+}
+
+/// Extracts the images in `archive` into `dest`, one entry at a time so memory use stays
+/// flat however large the archive. Folders inside the archive are flattened into the
+/// file name, while PDF and EPUB pages are numbered in reading order. Pages the
+/// archive's ComicInfo.xml marks as deleted are left out.
+pub fn extract_archive(
+    archive: &Path,
+    dest: &Path,
+    limits: &ExtractLimits,
+) -> anyhow::Result<Extracted> {
+    let format = ArchiveFormat::detect(archive)?;
+    let compressed_size = match format {
+        ArchiveFormat::Pdf => None,
+        _ => Some(std::fs::metadata(archive)?.len()),
+    };
+    let mut pages = Pages::new(dest, limits, compressed_size)?;
+    match format {
+        ArchiveFormat::Zip => extract_zip(archive, &mut pages)?,
+        ArchiveFormat::Rar => extract_rar(archive, &mut pages)?,
+        ArchiveFormat::SevenZip => extract_7z(archive, &mut pages)?,
+        ArchiveFormat::Tar => extract_tar(archive, &mut pages)?,
+        ArchiveFormat::Pdf => extract_pdf(archive, &mut pages)?,
+        ArchiveFormat::Epub => extract_epub(archive, &mut pages)?,
+    }
+    pages.finish()
+}
+
+fn extract_zip(archive: &Path, pages: &mut Pages) -> anyhow::Result<()> {
+    let mut archive = ZipArchive::new(File::open(archive)?)?;
+    for i in 0..archive.len() {
+        pages.next_entry()?;
+        let mut entry = archive.by_index(i)?;
+        if entry.is_dir() || entry.is_symlink() {
+            continue;
+        }
+        if is_comic_info(entry.name()) {
+            pages.read_comic_info(&mut entry)?;
+        } else if let Some(filename) = pages.accept(entry.name(), entry.size())? {
+            pages.write(&filename, &mut entry)?;
+        }
+    }
+    Ok(())
+}
+
+fn extract_rar(archive: &Path, pages: &mut Pages) -> anyhow::Result<()> {
+    let mut archive = RarArchive::open(archive)?;
+    while let Some(entry) = archive.next_entry()? {
+        pages.next_entry()?;
+        if !entry.is_file {
+            archive.skip()?;
+            continue;
+        }
+        if is_comic_info(&entry.name) && entry.size <= MAX_COMIC_INFO_SIZE {
+            let mut xml = Vec::new();
+            archive.read_to(&mut xml, MAX_COMIC_INFO_SIZE)?;
+            pages.read_comic_info(&mut xml.as_slice())?;
+        } else if let Some(filename) = pages.accept(&entry.name, entry.size)? {
+            let mut output = File::create_new(pages.path(&filename)?)?;
+            let written = archive.read_to(&mut output, pages.remaining())?;
+            pages.add(&filename, written)?;
+        } else {
+            archive.skip()?;
+        }
+    }
+    Ok(())
+}
+
+fn extract_7z(archive: &Path, pages: &mut Pages) -> anyhow::Result<()> {
+    let mut reader = sevenz_rust2::ArchiveReader::open(archive, sevenz_rust2::Password::empty())?;
+
+    // The callback can only fail with the 7z crate's error, so ours is kept aside
+    let mut failure = None;
+    reader.for_each_entries(|entry, data| {
+        let result = pages.next_entry().and_then(|()| {
+            if entry.is_directory() || entry.is_anti_item() {
+                return Ok(());
+            }
+            if is_comic_info(entry.name()) {
+                return pages.read_comic_info(data);
+            }
+            match pages.accept(entry.name(), entry.size())? {
+                Some(filename) => pages.write(&filename, data),
+                None => Ok(()),
+            }
+        });
+        match result {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                failure = Some(e);
+                Ok(false)
+            }
+        }
+    })?;
+
+    failure.map_or(Ok(()), Err)
+}
+
+fn extract_tar(archive: &Path, pages: &mut Pages) -> anyhow::Result<()> {
+    let mut archive = tar::Archive::new(File::open(archive)?);
+    for entry in archive.entries()? {
+        pages.next_entry()?;
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        if is_comic_info(&name) {
+            pages.read_comic_info(&mut entry)?;
+        } else if let Some(filename) = pages.accept(&name, entry.size())? {
+            pages.write(&filename, &mut entry)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn fixture(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/ingest/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn test_detects_format_from_contents() {
+        let dir = TempDir::new().unwrap();
+        // A CBR that was renamed to .cbz is still read as RAR
+        let renamed = dir.path().join("chapter.cbz");
+        std::fs::copy(fixture("pages.cbr"), &renamed).unwrap();
+
+        assert_eq!(ArchiveFormat::detect(&renamed).unwrap(), ArchiveFormat::Rar);
+        assert_eq!(
+            ArchiveFormat::detect(&fixture("pages.cb7")).unwrap(),
+            ArchiveFormat::SevenZip
+        );
+        assert_eq!(
+            ArchiveFormat::detect(&fixture("pages.cbt")).unwrap(),
+            ArchiveFormat::Tar
+        );
+
+        assert_eq!(
+            ArchiveFormat::detect(&fixture("pages.pdf")).unwrap(),
+            ArchiveFormat::Pdf
+        );
+        assert_eq!(
+            ArchiveFormat::detect(&fixture("pages.epub")).unwrap(),
+            ArchiveFormat::Epub
+        );
+
+        std::fs::write(dir.path().join("page.html"), b"<html></html>").unwrap();
+        assert!(ArchiveFormat::detect(&dir.path().join("page.html")).is_err());
+    }
+
+    #[test]
+    fn test_extracts_every_format() {
+        for name in ["pages.cbr", "pages.cb7", "pages.cbt"] {
+            let dir = TempDir::new().unwrap();
+            let archive = fixture(name);
+            let dest = dir.path().join("chapter_1");
+
+            let page_count = extract_archive(&archive, &dest, &ExtractLimits::default())
+                .unwrap()
+                .page_count;
+
+            assert_eq!(page_count, 2, "{name}");
+            assert_eq!(
+                std::fs::read(dest.join("pages_001.jpg")).unwrap(),
+                b"\xff\xd8\xffpage one",
+                "{name}"
+            );
+            assert!(dest.join("pages_002.jpg").exists(), "{name}");
+            assert!(!dest.join("notes.txt").exists(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_extracts_pdf_pages() {
+        let dir = TempDir::new().unwrap();
+        let dest = dir.path().join("chapter_1");
+
+        let page_count = extract_archive(&fixture("pages.pdf"), &dest, &ExtractLimits::default())
+            .unwrap()
+            .page_count;
+
+        // The scanned first page keeps its JPEG, the drawn second one is rendered
+        assert_eq!(page_count, 2);
+        let jpeg = std::fs::read(dest.join("0001.jpg")).unwrap();
+        assert!(jpeg.starts_with(b"\xff\xd8\xff"));
+        let png = std::fs::read(dest.join("0002.png")).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        // 100x150pt, scaled to the render height
+        assert_eq!(&png[16..24], &[0, 0, 6, 64, 0, 0, 9, 96]);
+    }
+
+    #[test]
+    fn test_extracts_epub_in_spine_order() {
+        let dir = TempDir::new().unwrap();
+        let dest = dir.path().join("chapter_1");
+
+        let page_count = extract_archive(&fixture("pages.epub"), &dest, &ExtractLimits::default())
+            .unwrap()
+            .page_count;
+
+        // The spine lists p2 before p1, and the logo is only on the contents page
+        assert_eq!(page_count, 2);
+        assert_eq!(
+            std::fs::read(dest.join("0001.jpg")).unwrap(),
+            b"\xff\xd8\xffpage a"
+        );
+        assert_eq!(
+            std::fs::read(dest.join("0002.jpg")).unwrap(),
+            b"\xff\xd8\xffpage b"
+        );
+        assert_eq!(std::fs::read_dir(&dest).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_limits_apply_to_every_format() {
+        let limits = ExtractLimits {
+            max_entries: 1,
+            ..ExtractLimits::default()
+        };
+        for name in [
+            "pages.cbr",
+            "pages.cb7",
+            "pages.cbt",
+            "pages.pdf",
+            "pages.epub",
+        ] {
+            let dir = TempDir::new().unwrap();
+            let result = extract_archive(&fixture(name), &dir.path().join("out"), &limits);
+            assert!(result.is_err(), "{name}");
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use roxmltree::Document;
+use serde::{Deserialize, Serialize};
+
+use crate::arrrrr::label::parse_chapter_number;
+
+/// Which way a chapter's pages are turned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum ReadingDirection {
+    Ltr,
+    Rtl,
+}
+
+impl FromStr for ReadingDirection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ltr" => Ok(Self::Ltr),
+            "rtl" => Ok(Self::Rtl),
+            _ => Err(anyhow!("reading_direction must be ltr or rtl")),
+        }
+    }
+}
+
+/// What a tagger recorded about a chapter in its archive's ComicInfo.xml.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ComicInfo {
+    pub series: Option<String>,
+    /// Chapter number and sub-chapter.
+    pub number: Option<(f64, i64)>,
+    pub volume: Option<i64>,
+    pub title: Option<String>,
+    pub writer: Option<String>,
+    pub language: Option<String>,
+    pub reading_direction: Option<ReadingDirection>,
+    /// Pages marked `Deleted`, counted from 0 over the archive's images in the order
+    /// it holds them.
+    pub deleted_pages: Vec<usize>,
+}
+
+impl ComicInfo {
+    pub fn parse(xml: &str) -> anyhow::Result<Self> {
+        let document = Document::parse(xml)?;
+        let root = document.root_element();
+        if !root.has_tag_name("ComicInfo") {
+            return Err(anyhow!("Not a ComicInfo document"));
+        }
+
+        let text = |name: &str| {
+            root.children()
+                .find(|n| n.has_tag_name(name))
+                .and_then(|n| n.text())
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+        };
+
+        // Manga is Unknown, No, Yes or YesAndRightToLeft; "Yes" alone says nothing about direction
+        let reading_direction = match text("Manga").as_deref() {
+            Some("YesAndRightToLeft") => Some(ReadingDirection::Rtl),
+            Some("No") => Some(ReadingDirection::Ltr),
+            _ => None,
+        };
+
+        let deleted_pages = root
+            .children()
+            .filter(|n| n.has_tag_name("Pages"))
+            .flat_map(|pages| pages.children())
+            .filter(|n| n.has_tag_name("Page") && n.attribute("Type") == Some("Deleted"))
+            .filter_map(|n| n.attribute("Image")?.parse().ok())
+            .collect();
+
+        Ok(Self {
+            series: text("Series"),
+            // Taggers write "12a" and the like for extras, which aren't a chapter number
+            number: text("Number").and_then(|n| parse_chapter_number(&n)),
+            // -1 is the schema's "unknown"
+            volume: text("Volume")
+                .and_then(|v| v.parse().ok())
+                .filter(|v| *v >= 0),
+            title: text("Title"),
+            writer: text("Writer"),
+            language: text("LanguageISO"),
+            reading_direction,
+            deleted_pages,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let info = ComicInfo::parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+            <ComicInfo xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+              <Series>Chainsaw Man</Series>
+              <Number>12.5</Number>
+              <Volume>2</Volume>
+              <Title> Kill Denji </Title>
+              <Writer>Tatsuki Fujimoto</Writer>
+              <LanguageISO>en</LanguageISO>
+              <Manga>YesAndRightToLeft</Manga>
+              <Pages>
+                <Page Image="0" Type="FrontCover" />
+                <Page Image="1" Type="Deleted" />
+                <Page Image="2" />
+              </Pages>
+            </ComicInfo>"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            info,
+            ComicInfo {
+                series: Some("Chainsaw Man".into()),
+                number: Some((12.5, 5)),
+                volume: Some(2),
+                title: Some("Kill Denji".into()),
+                writer: Some("Tatsuki Fujimoto".into()),
+                language: Some("en".into()),
+                reading_direction: Some(ReadingDirection::Rtl),
+                deleted_pages: vec![1],
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_leaves_unknowns_empty() {
+        let info = ComicInfo::parse(
+            "<ComicInfo><Number>12a</Number><Volume>-1</Volume><Manga>Yes</Manga><Title/></ComicInfo>",
+        )
+        .unwrap();
+
+        assert_eq!(info, ComicInfo::default());
+        assert!(ComicInfo::parse("<comic/>").is_err());
+
+        for number in ["NaN", "inf", "-3"] {
+            let xml = format!("<ComicInfo><Number>{number}</Number></ComicInfo>");
+            assert_eq!(ComicInfo::parse(&xml).unwrap().number, None, "{number}");
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::anyhow;
+use roxmltree::Document;
+use zip::ZipArchive;
+
+use super::Pages;
+
+// Container, package and page documents are small; anything past this isn't one
+const MAX_DOCUMENT_SIZE: u64 = 4 * 1024 * 1024;
+
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+
+/// Writes the images of the EPUB at `path` in spine order, numbering them so the
+/// chapter reads the way the book does. Images that no spine document shows, such as
+/// ones only on the contents page, are left out.
+pub(super) fn extract_epub(path: &Path, pages: &mut Pages) -> anyhow::Result<()> {
+    let mut zip = ZipArchive::new(File::open(path)?)?;
+
+    let container = read_document(&mut zip, "META-INF/container.xml")?;
+    let package_path = Document::parse(&container)?
+        .descendants()
+        .find(|n| n.has_tag_name("rootfile"))
+        .and_then(|n| n.attribute("full-path"))
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("EPUB container names no package document"))?;
+
+    let package = read_document(&mut zip, &package_path)?;
+    let mut number = 0;
+    for item in spine(&package, &package_path)? {
+        let images = match item.media_type.starts_with("image/") {
+            true => vec![item.path],
+            false => {
+                let document = read_document(&mut zip, &item.path)?;
+                document_images(&document, &item.path)?
+            }
+        };
+
+        for image in images {
+            pages.next_entry()?;
+            let Some((_, extension)) = image.rsplit_once('.') else {
+                continue;
+            };
+            let filename = format!("{:04}.{}", number + 1, extension.to_lowercase());
+            let mut entry = zip
+                .by_name(&image)
+                .map_err(|_| anyhow!("EPUB is missing {image}"))?;
+            if let Some(filename) = pages.accept(&filename, entry.size())? {
+                pages.write(&filename, &mut entry)?;
+                number += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+struct SpineItem {
+    path: String,
+    media_type: String,
+}
+
+// The package's spine, each item resolved through the manifest to its path in the zip
+fn spine(package: &str, package_path: &str) -> anyhow::Result<Vec<SpineItem>> {
+    let package = Document::parse(package)?;
+    let manifest: Vec<_> = package
+        .descendants()
+        .filter(|n| n.has_tag_name("item"))
+        .collect();
+
+    package
+        .descendants()
+        .filter(|n| n.has_tag_name("itemref"))
+        .filter(|n| n.attribute("linear") != Some("no"))
+        .filter_map(|n| n.attribute("idref"))
+        .map(|idref| {
+            let item = manifest
+                .iter()
+                .find(|item| item.attribute("id") == Some(idref))
+                .ok_or_else(|| anyhow!("EPUB spine refers to missing item {idref}"))?;
+            let href = item
+                .attribute("href")
+                .ok_or_else(|| anyhow!("EPUB item {idref} has no href"))?;
+            Ok(SpineItem {
+                path: resolve(package_path, href),
+                media_type: item.attribute("media-type").unwrap_or("").to_string(),
+            })
+        })
+        .collect()
+}
+
+// Images an XHTML page shows, in document order. Fixed-layout books wrap pages in
+// either an <img> or an SVG <image>.
+fn document_images(document: &str, document_path: &str) -> anyhow::Result<Vec<String>> {
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let document = Document::parse_with_options(document, options)?;
+
+    let images = document
+        .descendants()
+        .filter_map(|n| match n.tag_name().name() {
+            "img" => n.attribute("src"),
+            "image" => n
+                .attribute((XLINK_NS, "href"))
+                .or_else(|| n.attribute("href")),
+            _ => None,
+        })
+        .filter(|src| !src.contains(':'))
+        .map(|src| resolve(document_path, src))
+        .collect();
+    Ok(images)
+}
+
+// `href` as a path in the zip, taken relative to the document at `base`
+fn resolve(base: &str, href: &str) -> String {
+    let href = href.split(['#', '?']).next().unwrap_or("");
+    let href = urlencoding::decode(href).map_or_else(|_| href.into(), |h| h.into_owned());
+
+    let mut parts: Vec<&str> = base.split('/').collect();
+    parts.pop();
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+fn read_document(zip: &mut ZipArchive<File>, name: &str) -> anyhow::Result<String> {
+    let entry = zip
+        .by_name(name)
+        .map_err(|_| anyhow!("EPUB is missing {name}"))?;
+    let mut document = String::new();
+    entry
+        .take(MAX_DOCUMENT_SIZE)
+        .read_to_string(&mut document)?;
+    Ok(document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(
+            resolve("OEBPS/content.opf", "text/p1.xhtml"),
+            "OEBPS/text/p1.xhtml"
+        );
+        assert_eq!(
+            resolve("OEBPS/text/p1.xhtml", "../images/page%20b.jpg#frag"),
+            "OEBPS/images/page b.jpg"
+        );
+        assert_eq!(resolve("content.opf", "p1.xhtml"), "p1.xhtml");
+        assert_eq!(resolve("content.opf", "../../p1.xhtml"), "p1.xhtml");
+    }
+}
@@ -0,0 +1,450 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+
+mod archive;
+mod comic_info;
+mod epub;
+mod pdf;
+mod rar;
+
+pub use archive::{ArchiveFormat, extract_archive};
+pub use comic_info::{ComicInfo, ReadingDirection};
+
+/// The pages of an upload, and what its ComicInfo.xml says about them if it had one.
+#[derive(Debug)]
+pub struct Extracted {
+    pub page_count: usize,
+    pub comic_info: Option<ComicInfo>,
+}
+
+/// Bounds on what an archive may expand to, so a crafted upload can't fill the disk.
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractLimits {
+    pub max_entries: usize,
+    /// Decompressed bytes across all pages.
+    pub max_total_size: u64,
+    /// Largest ratio of decompressed pages to the archive's own size, past `RATIO_GRACE` bytes.
+    pub max_ratio: u64,
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 10_000,
+            max_total_size: 8 * 1024 * 1024 * 1024,
+            max_ratio: 100,
+        }
+    }
+}
+
+// Real ones are a few KiB
+const MAX_COMIC_INFO_SIZE: u64 = 1024 * 1024;
+
+// Small images, blank pages especially, compress far better than photos, so the ratio
+// only counts once the pages have grown past this
+const RATIO_GRACE: u64 = 1024 * 1024;
+
+// Writes an archive's pages into the chapter directory, holding every format to the
+// same names and limits. Sizes an archive declares can lie, so they're checked again
+// against what was actually written.
+struct Pages<'a> {
+    dest: &'a Path,
+    max_entries: usize,
+    budget: u64,
+    entries: usize,
+    written: u64,
+    /// File names of the pages written, in archive order.
+    pages: Vec<String>,
+    comic_info: Option<String>,
+}
+
+impl<'a> Pages<'a> {
+    /// `compressed_size` is what the ratio is measured against, None when the pages
+    /// aren't decompressed from the upload but produced from it, like rendered PDF pages.
+    fn new(
+        dest: &'a Path,
+        limits: &ExtractLimits,
+        compressed_size: Option<u64>,
+    ) -> anyhow::Result<Self> {
+        let budget = match compressed_size {
+            Some(size) => limits
+                .max_total_size
+                .min(size.saturating_mul(limits.max_ratio).max(RATIO_GRACE)),
+            None => limits.max_total_size,
+        };
+        std::fs::create_dir_all(dest)?;
+
+        Ok(Self {
+            dest,
+            max_entries: limits.max_entries,
+            budget,
+            entries: 0,
+            written: 0,
+            pages: Vec::new(),
+            comic_info: None,
+        })
+    }
+
+    /// Counts an entry of any kind against `max_entries`.
+    fn next_entry(&mut self) -> anyhow::Result<()> {
+        self.entries += 1;
+        if self.entries > self.max_entries {
+            return Err(anyhow!(
+                "Archive has more than the {} entries allowed",
+                self.max_entries
+            ));
+        }
+        Ok(())
+    }
+
+    /// The file name the entry is written to, or None when it isn't a page.
+    fn accept(&self, entry_name: &str, size: u64) -> anyhow::Result<Option<String>> {
+        if !is_image_file(entry_name) {
+            return Ok(None);
+        }
+        let Some(filename) = page_name(entry_name) else {
+            return Ok(None);
+        };
+        if size > self.remaining() {
+            return Err(too_large(&filename));
+        }
+        Ok(Some(filename))
+    }
+
+    /// Where `filename` goes, which must not be taken already.
+    fn path(&self, filename: &str) -> anyhow::Result<PathBuf> {
+        let path = self.dest.join(filename);
+        if path.symlink_metadata().is_ok() {
+            return Err(anyhow!("Archive has more than one page named {filename}"));
+        }
+        Ok(path)
+    }
+
+    fn write(&mut self, filename: &str, reader: &mut dyn Read) -> anyhow::Result<()> {
+        let mut output = File::create_new(self.path(filename)?)?;
+        let written = io::copy(&mut reader.take(self.remaining() + 1), &mut output)?;
+        self.add(filename, written)
+    }
+
+    /// Records a page already written to `path(filename)`.
+    fn add(&mut self, filename: &str, size: u64) -> anyhow::Result<()> {
+        if size > self.remaining() {
+            return Err(too_large(filename));
+        }
+        self.written += size;
+        self.pages.push(filename.to_string());
+        Ok(())
+    }
+
+    fn remaining(&self) -> u64 {
+        self.budget - self.written
+    }
+
+    fn read_comic_info(&mut self, reader: &mut dyn Read) -> anyhow::Result<()> {
+        let mut xml = Vec::new();
+        reader.take(MAX_COMIC_INFO_SIZE).read_to_end(&mut xml)?;
+        self.comic_info = Some(String::from_utf8_lossy(&xml).into_owned());
+        Ok(())
+    }
+
+    // Taggers get ComicInfo.xml wrong often enough that a broken one is ignored rather
+    // than failing the upload
+    fn finish(self) -> anyhow::Result<Extracted> {
+        let comic_info = self
+            .comic_info
+            .as_deref()
+            .and_then(|xml| match ComicInfo::parse(xml) {
+                Ok(info) => Some(info),
+                Err(e) => {
+                    eprintln!("Ignoring unreadable ComicInfo.xml: {e:?}");
+                    None
+                }
+            });
+
+        // Page numbers count images in the order the archive holds them, which flattened
+        // names don't always sort back into
+        let mut page_count = self.pages.len();
+        if let Some(info) = &comic_info {
+            for (i, filename) in self.pages.iter().enumerate() {
+                if info.deleted_pages.contains(&i) {
+                    std::fs::remove_file(self.dest.join(filename))?;
+                    page_count -= 1;
+                }
+            }
+        }
+
+        if page_count == 0 {
+            return Err(anyhow!("No image files found in archive"));
+        }
+        Ok(Extracted {
+            page_count,
+            comic_info,
+        })
+    }
+}
+
+// Taggers put it at the root of the archive
+fn is_comic_info(entry_name: &str) -> bool {
+    entry_name
+        .trim_start_matches(['/', '\\'])
+        .eq_ignore_ascii_case("ComicInfo.xml")
+}
+
+fn too_large(filename: &str) -> anyhow::Error {
+    anyhow!("{filename} decompresses to more than the upload limits allow")
+}
+
+// A flat file name for an entry that can only ever land inside the chapter directory.
+// "vol1/ch2/001.jpg" becomes "vol1_ch2_001.jpg", keeping pages from different folders
+// apart and in order. Parent, root and drive components are dropped, as are macOS
+// metadata entries, which have image extensions but aren't images.
+fn page_name(entry_name: &str) -> Option<String> {
+    let parts: Vec<&str> = entry_name
+        .split(['/', '\\'])
+        .filter(|part| !part.is_empty() && *part != "." && *part != "..")
+        .filter(|part| !(part.len() == 2 && part.ends_with(':')))
+        .collect();
+
+    if parts
+        .iter()
+        .any(|part| part.starts_with('.') || *part == "__MACOSX")
+    {
+        return None;
+    }
+    let name = parts.join("_");
+    (!name.is_empty()).then_some(name)
+}
+
+pub fn is_image_file(filename: &str) -> bool {
+    let lower = filename.to_lowercase();
+    lower.ends_with(".jpg")
+        || lower.ends_with(".jpeg")
+        || lower.ends_with(".png")
+        || lower.ends_with(".gif")
+        || lower.ends_with(".webp")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+    use zip::write::SimpleFileOptions;
+
+    /// A zip of `entries`, written to a file in `dir`.
+    pub(crate) fn write_zip(dir: &Path, entries: &[(&str, &[u8])]) -> std::path::PathBuf {
+        let path = dir.join("chapter.cbz");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        for (name, data) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn test_extract_cbz() {
+        let dir = TempDir::new().unwrap();
+        let archive = write_zip(
+            dir.path(),
+            &[
+                ("001.jpg", b"first"),
+                ("002.PNG", b"second"),
+                ("notes.txt", b"not a page"),
+            ],
+        );
+        let dest = dir.path().join("chapter_1");
+
+        assert_eq!(
+            extract_archive(&archive, &dest, &ExtractLimits::default())
+                .unwrap()
+                .page_count,
+            2
+        );
+        assert_eq!(std::fs::read(dest.join("001.jpg")).unwrap(), b"first");
+        assert!(!dest.join("notes.txt").exists());
+    }
+
+    #[test]
+    fn test_rejects_archives_without_pages() {
+        let dir = TempDir::new().unwrap();
+        let archive = write_zip(dir.path(), &[("readme.txt", b"hi")]);
+
+        let limits = ExtractLimits::default();
+        assert!(extract_archive(&archive, &dir.path().join("out"), &limits).is_err());
+
+        std::fs::write(dir.path().join("broken.cbz"), b"not a zip").unwrap();
+        let broken = dir.path().join("broken.cbz");
+        assert!(extract_archive(&broken, &dir.path().join("broken"), &limits).is_err());
+    }
+
+    #[test]
+    fn test_reads_comic_info() {
+        let dir = TempDir::new().unwrap();
+        let xml = br#"<ComicInfo><Number>3</Number><Pages><Page Image="1" Type="Deleted"/></Pages></ComicInfo>"#;
+        let archive = write_zip(
+            dir.path(),
+            &[
+                ("ComicInfo.xml", xml),
+                ("001.jpg", b"1"),
+                ("002.jpg", b"scanlator credits"),
+                ("003.jpg", b"3"),
+            ],
+        );
+        let dest = dir.path().join("chapter_3");
+
+        let extracted = extract_archive(&archive, &dest, &ExtractLimits::default()).unwrap();
+
+        assert_eq!(extracted.page_count, 2);
+        assert_eq!(extracted.comic_info.unwrap().number, Some((3.0, 0)));
+        assert_eq!(files(&dest), vec!["001.jpg", "003.jpg"]);
+
+        // A broken one doesn't stop the pages going in
+        let archive = write_zip(
+            dir.path(),
+            &[("ComicInfo.xml", b"<ComicInfo>"), ("001.jpg", b"1")],
+        );
+        let extracted =
+            extract_archive(&archive, &dir.path().join("out"), &ExtractLimits::default()).unwrap();
+        assert_eq!(extracted.page_count, 1);
+        assert!(extracted.comic_info.is_none());
+    }
+
+    #[test]
+    fn test_deletes_pages_by_archive_order() {
+        let dir = TempDir::new().unwrap();
+        // Image 1 is b/001.jpg: the entries that aren't pages don't count, and sorted by
+        // flattened name it would be a_002.jpg
+        let xml = br#"<ComicInfo><Pages><Page Image="1" Type="Deleted"/></Pages></ComicInfo>"#;
+        let archive = write_zip(
+            dir.path(),
+            &[
+                ("ComicInfo.xml", xml),
+                ("a/001.jpg", b"0"),
+                ("a/notes.txt", b"not a page"),
+                ("__MACOSX/b/._001.jpg", b"not a page either"),
+                ("b/001.jpg", b"scanlator credits"),
+                ("a/002.jpg", b"2"),
+            ],
+        );
+        let dest = dir.path().join("chapter_1");
+
+        let extracted = extract_archive(&archive, &dest, &ExtractLimits::default()).unwrap();
+
+        assert_eq!(extracted.page_count, 2);
+        assert_eq!(files(&dest), vec!["a_001.jpg", "a_002.jpg"]);
+    }
+
+    fn files(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_page_name() {
+        assert_eq!(page_name("001.jpg").as_deref(), Some("001.jpg"));
+        assert_eq!(
+            page_name("vol1/ch2/001.jpg").as_deref(),
+            Some("vol1_ch2_001.jpg")
+        );
+        assert_eq!(
+            page_name("../../etc/001.jpg").as_deref(),
+            Some("etc_001.jpg")
+        );
+        assert_eq!(page_name("/abs/001.jpg").as_deref(), Some("abs_001.jpg"));
+        assert_eq!(
+            page_name("C:\\pages\\001.jpg").as_deref(),
+            Some("pages_001.jpg")
+        );
+        assert_eq!(page_name("__MACOSX/._001.jpg"), None);
+        assert_eq!(page_name(".hidden.jpg"), None);
+        assert_eq!(page_name("../.."), None);
+    }
+
+    #[test]
+    fn test_entries_stay_inside_dest() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("evil.cbz");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        for name in [
+            "../../escaped.jpg",
+            "/tmp/absolute.jpg",
+            "nested/dir/003.jpg",
+        ] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(b"page").unwrap();
+        }
+        zip.add_symlink("link.jpg", "/etc/passwd", SimpleFileOptions::default())
+            .unwrap();
+        zip.finish().unwrap();
+        let dest = dir.path().join("a/b/chapter");
+
+        extract_archive(&path, &dest, &ExtractLimits::default()).unwrap();
+
+        assert_eq!(
+            files(&dest),
+            vec!["escaped.jpg", "nested_dir_003.jpg", "tmp_absolute.jpg"]
+        );
+        assert!(!dir.path().join("a/escaped.jpg").exists());
+        assert!(!dir.path().join("escaped.jpg").exists());
+    }
+
+    #[test]
+    fn test_rejects_colliding_names() {
+        let dir = TempDir::new().unwrap();
+        let archive = write_zip(dir.path(), &[("a/001.jpg", b"one"), ("a_001.jpg", b"two")]);
+
+        let err = extract_archive(&archive, &dir.path().join("out"), &ExtractLimits::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("a_001.jpg"));
+    }
+
+    #[test]
+    fn test_rejects_too_many_entries() {
+        let dir = TempDir::new().unwrap();
+        let archive = write_zip(
+            dir.path(),
+            &[("1.jpg", b"1"), ("2.jpg", b"2"), ("3.jpg", b"3")],
+        );
+        let limits = ExtractLimits {
+            max_entries: 2,
+            ..ExtractLimits::default()
+        };
+
+        assert!(extract_archive(&archive, &dir.path().join("out"), &limits).is_err());
+    }
+
+    #[test]
+    fn test_rejects_archive_bombs() {
+        let dir = TempDir::new().unwrap();
+        // 8 MiB of zeros deflates to a few KiB
+        let zeros = vec![0u8; 8 * 1024 * 1024];
+        let archive = write_zip(dir.path(), &[("bomb.jpg", &zeros)]);
+
+        let ratio = extract_archive(
+            &archive,
+            &dir.path().join("ratio"),
+            &ExtractLimits::default(),
+        );
+        assert!(ratio.unwrap_err().to_string().contains("bomb.jpg"));
+
+        let limits = ExtractLimits {
+            max_total_size: 1024,
+            max_ratio: u64::MAX,
+            ..ExtractLimits::default()
+        };
+        let archive = write_zip(
+            dir.path(),
+            &[("001.jpg", &[1; 600]), ("002.jpg", &[2; 600])],
+        );
+        assert!(extract_archive(&archive, &dir.path().join("total"), &limits).is_err());
+    }
+}
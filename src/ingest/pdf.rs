@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+use hayro::hayro_interpret::InterpreterSettings;
+use hayro::hayro_syntax::content::ops::TypedInstruction;
+use hayro::hayro_syntax::object::Name;
+use hayro::hayro_syntax::page::Page;
+use hayro::hayro_syntax::{Filter, Pdf};
+use hayro::vello_cpu::color::palette::css::WHITE;
+use hayro::{PixmapSettings, RenderCache, RenderSettings};
+
+use super::Pages;
+
+// Rendered pages are scaled to this height, about what a scan of a printed volume comes
+// out at. Widths are capped in proportion, so an odd page size can't blow up a render.
+const RENDER_HEIGHT: f32 = 2400.0;
+const MAX_RENDER_WIDTH: f32 = RENDER_HEIGHT * 4.0;
+
+/// Writes one image per page of the PDF at `path`, in page order. Scanned pages that are
+/// a single JPEG are copied out untouched; anything else is rendered on the CPU to PNG.
+pub(super) fn extract_pdf(path: &Path, pages: &mut Pages) -> anyhow::Result<()> {
+    // The parser works on the whole file in memory
+    let pdf = Pdf::new(std::fs::read(path)?).map_err(|e| anyhow!("Unreadable PDF: {e:?}"))?;
+    let cache = RenderCache::new();
+
+    for (i, page) in pdf.pages().iter().enumerate() {
+        pages.next_entry()?;
+        let number = i + 1;
+
+        match scanned_jpeg(page) {
+            Some(jpeg) => {
+                pages.write(&format!("{number:04}.jpg"), &mut jpeg.as_slice())?;
+            }
+            None => {
+                let png = render(page, &cache)?;
+                pages.write(&format!("{number:04}.png"), &mut png.as_slice())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// The JPEG a page consists of, when it draws nothing but that one image. Colour spaces
+// other than plain grey and RGB, and Decode arrays, change how the JPEG is meant to look,
+// so those pages are rendered instead.
+fn scanned_jpeg(page: &Page) -> Option<Vec<u8>> {
+    let mut image = None;
+    let mut ops = page.typed_operations();
+    while let Some(op) = ops.next() {
+        match op {
+            TypedInstruction::SaveState(_)
+            | TypedInstruction::RestoreState(_)
+            | TypedInstruction::Transform(_) => {}
+            TypedInstruction::XObject(name) if image.is_none() => {
+                let stream = page.resources().get_x_object(name.0)?;
+                let dict = stream.dict();
+                let is_jpeg = dict.get::<Name>(b"Subtype")?.as_str() == "Image"
+                    && stream.filters().as_slice() == [Filter::DctDecode]
+                    && matches!(
+                        dict.get::<Name>(b"ColorSpace")?.as_str(),
+                        "DeviceGray" | "DeviceRGB"
+                    )
+                    && !dict.contains_key(b"Decode");
+                if !is_jpeg {
+                    return None;
+                }
+                image = Some(stream.raw_data().into_owned());
+            }
+            _ => return None,
+        }
+    }
+    image
+}
+
+fn render<'a>(page: &'a Page<'a>, cache: &RenderCache<'a>) -> anyhow::Result<Vec<u8>> {
+    let (width, height) = page.render_dimensions();
+    if width <= 0.0 || height <= 0.0 {
+        return Err(anyhow!("PDF page has no area"));
+    }
+    let scale = (RENDER_HEIGHT / height).min(MAX_RENDER_WIDTH / width);
+
+    let pixmap = hayro::render(
+        page,
+        cache,
+        &InterpreterSettings::default(),
+        &RenderSettings::default(),
+        &PixmapSettings {
+            x_scale: scale,
+            y_scale: scale,
+            bg_color: WHITE,
+        },
+    );
+    pixmap
+        .into_png()
+        .map_err(|e| anyhow!("Could not encode rendered page: {e}"))
+}
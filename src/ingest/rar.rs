@@ -0,0 +1,198 @@
+use std::ffi::CString;
+use std::io::Write;
+use std::os::raw::c_int;
+use std::path::Path;
+use std::ptr::{self, NonNull};
+
+use anyhow::anyhow;
+use unrar_sys::{
+    ERAR_END_ARCHIVE, ERAR_SUCCESS, Handle, HeaderDataEx, LPARAM, OpenArchiveDataEx,
+    RAR_OM_EXTRACT, RAR_SKIP, RAR_TEST, RAR_VOL_ASK, RHDF_DIRECTORY, UCM_CHANGEVOLUME,
+    UCM_CHANGEVOLUMEW, UCM_PROCESSDATA, UINT, WCHAR,
+};
+
+// Unix file type bits, for archives made on Unix (host OS 3) that store links as files
+const HOST_UNIX: u32 = 3;
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+/// A RAR archive read one entry at a time. The unrar crate only extracts whole entries
+/// to a file or into memory, so entries are streamed through unrar's data callback
+/// instead, which lets them be cut off as soon as they outgrow what's allowed.
+pub(super) struct RarArchive {
+    handle: NonNull<Handle>,
+}
+
+pub(super) struct RarEntry {
+    pub name: String,
+    pub size: u64,
+    /// False for folders and links, which are skipped rather than followed.
+    pub is_file: bool,
+}
+
+impl RarArchive {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let name = CString::new(path.as_os_str().as_encoded_bytes())?;
+        let mut data = OpenArchiveDataEx {
+            archive_name: name.as_ptr(),
+            archive_name_w: ptr::null(),
+            open_mode: RAR_OM_EXTRACT,
+            open_result: 0,
+            comment_buffer: ptr::null_mut(),
+            comment_buffer_size: 0,
+            comment_size: 0,
+            comment_state: 0,
+            flags: 0,
+            callback: None,
+            user_data: 0,
+            op_flags: 0,
+            comment_buffer_w: ptr::null_mut(),
+            reserved: [0; 25],
+        };
+        let handle = NonNull::new(unsafe { unrar_sys::RAROpenArchiveEx(&raw mut data) } as *mut _);
+        match handle {
+            Some(handle) if data.open_result == ERAR_SUCCESS as u32 => Ok(Self { handle }),
+            handle => {
+                if let Some(handle) = handle {
+                    drop(Self { handle });
+                }
+                Err(rar_error("open", data.open_result as c_int))
+            }
+        }
+    }
+
+    /// The header of the next entry, whose contents must then be read or skipped.
+    pub fn next_entry(&mut self) -> anyhow::Result<Option<RarEntry>> {
+        let mut header = HeaderDataEx::default();
+        match unsafe { unrar_sys::RARReadHeaderEx(self.handle.as_ptr(), &raw mut header) } {
+            ERAR_SUCCESS => {}
+            ERAR_END_ARCHIVE => return Ok(None),
+            code => return Err(rar_error("read", code)),
+        }
+
+        let link = header.redir_type != 0
+            || (header.host_os == HOST_UNIX && header.file_attr & S_IFMT == S_IFLNK);
+        Ok(Some(RarEntry {
+            name: wide_string(&header.filename_w),
+            size: (header.unp_size_high as u64) << 32 | header.unp_size as u64,
+            is_file: header.flags & RHDF_DIRECTORY == 0 && !link,
+        }))
+    }
+
+    pub fn skip(&mut self) -> anyhow::Result<()> {
+        self.process(RAR_SKIP, None)
+    }
+
+    /// Writes the current entry to `output`, stopping as soon as more than `limit` bytes
+    /// have come out of it. Returns how many bytes were written, which is `limit + 1`
+    /// for an entry that didn't fit.
+    pub fn read_to(&mut self, output: &mut dyn Write, limit: u64) -> anyhow::Result<u64> {
+        let mut sink = Sink {
+            output,
+            limit,
+            written: 0,
+            error: None,
+        };
+        let result = self.process(RAR_TEST, Some(&mut sink));
+        if let Some(e) = sink.error {
+            return Err(e.into());
+        }
+        if sink.written > limit {
+            return Ok(sink.written);
+        }
+        result.map(|()| sink.written)
+    }
+
+    fn process(&mut self, operation: c_int, sink: Option<&mut Sink>) -> anyhow::Result<()> {
+        let user_data = sink.map_or(0, |sink| sink as *mut Sink as LPARAM);
+        let code = unsafe {
+            unrar_sys::RARSetCallback(self.handle.as_ptr(), Some(callback), user_data);
+            unrar_sys::RARProcessFileW(self.handle.as_ptr(), operation, ptr::null(), ptr::null())
+        };
+        match code {
+            ERAR_SUCCESS => Ok(()),
+            code => Err(rar_error("extract", code)),
+        }
+    }
+}
+
+impl Drop for RarArchive {
+    fn drop(&mut self) {
+        unsafe { unrar_sys::RARCloseArchive(self.handle.as_ptr()) };
+    }
+}
+
+struct Sink<'a> {
+    output: &'a mut dyn Write,
+    limit: u64,
+    written: u64,
+    error: Option<std::io::Error>,
+}
+
+extern "C" fn callback(msg: UINT, user_data: LPARAM, p1: LPARAM, p2: LPARAM) -> c_int {
+    match msg {
+        // Multi-volume archives aren't supported, so there's never a next volume to find
+        UCM_CHANGEVOLUME | UCM_CHANGEVOLUMEW if p2 == RAR_VOL_ASK => -1,
+        UCM_PROCESSDATA if user_data != 0 => {
+            let sink = unsafe { &mut *(user_data as *mut Sink) };
+            let data = unsafe { std::slice::from_raw_parts(p1 as *const u8, p2 as usize) };
+            // Like `Read::take(limit + 1)`: one byte past the limit is enough to know
+            let room = (sink.limit + 1 - sink.written).min(data.len() as u64) as usize;
+            if let Err(e) = sink.output.write_all(&data[..room]) {
+                sink.error = Some(e);
+                return -1;
+            }
+            sink.written += room as u64;
+            if sink.written > sink.limit { -1 } else { 0 }
+        }
+        _ => 0,
+    }
+}
+
+fn wide_string(wide: &[WCHAR]) -> String {
+    wide.iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| char::from_u32(c as u32).unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+fn rar_error(action: &str, code: c_int) -> anyhow::Error {
+    anyhow!("Could not {action} RAR archive (unrar error {code})")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("src/ingest/fixtures/pages.cbr")
+    }
+
+    #[test]
+    fn test_reads_entries_in_order() {
+        let mut archive = RarArchive::open(&fixture()).unwrap();
+        let mut names = Vec::new();
+        while let Some(entry) = archive.next_entry().unwrap() {
+            let mut data = Vec::new();
+            let written = archive.read_to(&mut data, entry.size).unwrap();
+            assert_eq!(written, entry.size);
+            assert_eq!(data.len() as u64, entry.size);
+            names.push(entry.name);
+        }
+
+        assert_eq!(names, ["pages/001.jpg", "pages/002.jpg", "notes.txt"]);
+    }
+
+    #[test]
+    fn test_stops_reading_past_limit() {
+        let mut archive = RarArchive::open(&fixture()).unwrap();
+        let entry = archive.next_entry().unwrap().unwrap();
+        assert!(entry.size > 5);
+
+        let mut data = Vec::new();
+        let written = archive.read_to(&mut data, 4).unwrap();
+
+        assert_eq!(written, 5);
+        assert_eq!(data, b"\xff\xd8\xffpa");
+    }
+}
@@ -0,0 +1,642 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+use tokio::fs;
+use tokio::sync::{Notify, broadcast};
+use url::Url;
+
+use crate::AppState;
+use crate::arrrrr::label::chapter_dir;
+
+// How long an idle worker sleeps before polling the table again, in case a
+// notification was missed.
+const IDLE_POLL: Duration = Duration::from_secs(5);
+
+// Subscribers that fall further behind than this skip ahead to newer events.
+const EVENT_BUFFER: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadJob {
+    pub id: i64,
+    pub manga_id: i64,
+    pub source: String,
+    pub chapter_url: String,
+    pub chapter_number: f64,
+    pub sub_chapter: i64,
+    pub chapter_title: Option<String>,
+    pub status: JobStatus,
+    pub pages_done: i64,
+    pub pages_total: Option<i64>,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Progress notifications published to everyone subscribed to the queue.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum JobEvent {
+    Progress {
+        job_id: i64,
+        pages_done: i64,
+        pages_total: i64,
+    },
+    Completed {
+        job_id: i64,
+    },
+    Failed {
+        job_id: i64,
+        error: String,
+    },
+}
+
+impl JobEvent {
+    pub fn job_id(&self) -> i64 {
+        match self {
+            JobEvent::Progress { job_id, .. }
+            | JobEvent::Completed { job_id }
+            | JobEvent::Failed { job_id, .. } => *job_id,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobEvent::Progress { .. } => "progress",
+            JobEvent::Completed { .. } => "completed",
+            JobEvent::Failed { .. } => "failed",
+        }
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, JobEvent::Progress { .. })
+    }
+}
+
+impl JobStatus {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, JobStatus::Completed | JobStatus::Failed)
+    }
+}
+
+pub struct NewJob {
+    pub manga_id: i64,
+    pub source: String,
+    pub chapter_url: String,
+    pub chapter_number: f64,
+    pub sub_chapter: i64,
+    pub chapter_title: Option<String>,
+}
+
+/// Download jobs persisted in SQLite and worked off by a pool of tokio tasks.
+#[derive(Clone)]
+pub struct JobQueue {
+    pool: Pool<Sqlite>,
+    state: AppState,
+    notify: Arc<Notify>,
+    events: broadcast::Sender<JobEvent>,
+}
+
+impl JobQueue {
+    pub fn new(pool: Pool<Sqlite>, state: AppState) -> Self {
+        Self {
+            pool,
+            state,
+            notify: Arc::new(Notify::new()),
+            events: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+
+    /// Requeues anything left `running` by a previous process and spawns `workers` tasks.
+    pub async fn start(&self, workers: usize) -> anyhow::Result<()> {
+        self.requeue_interrupted().await?;
+        for _ in 0..workers.max(1) {
+            let queue = self.clone();
+            tokio::spawn(async move { queue.run_worker().await });
+        }
+        Ok(())
+    }
+
+    pub async fn enqueue(&self, job: NewJob) -> anyhow::Result<i64> {
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO download_jobs
+                (manga_id, source, chapter_url, chapter_number, sub_chapter, chapter_title)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING id as "id!"
+            "#,
+            job.manga_id,
+            job.source,
+            job.chapter_url,
+            job.chapter_number,
+            job.sub_chapter,
+            job.chapter_title
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.notify.notify_one();
+        Ok(id)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+        self.events.subscribe()
+    }
+
+    pub async fn get(&self, id: i64) -> anyhow::Result<Option<DownloadJob>> {
+        let job = sqlx::query_as!(
+            DownloadJob,
+            r#"
+            SELECT id as "id!", manga_id, source, chapter_url, chapter_number, sub_chapter,
+                   chapter_title,
+                   status as "status: JobStatus", pages_done, pages_total, error,
+                   created_at as "created_at: String", updated_at as "updated_at: String"
+            FROM download_jobs
+            WHERE id = ?
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(job)
+    }
+
+    /// Chapter and sub-chapter numbers of a manga that are already waiting for or being
+    /// downloaded.
+    pub async fn pending_chapters(&self, manga_id: i64) -> anyhow::Result<Vec<(f64, i64)>> {
+        let chapters = sqlx::query!(
+            r#"
+            SELECT chapter_number, sub_chapter FROM download_jobs
+            WHERE manga_id = ? AND status IN ('queued', 'running')
+            "#,
+            manga_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(chapters
+            .into_iter()
+            .map(|c| (c.chapter_number, c.sub_chapter))
+            .collect())
+    }
+
+    /// Puts a failed job back in the queue. Pages it already fetched are kept.
+    pub async fn retry(&self, id: i64) -> anyhow::Result<bool> {
+        let retried = sqlx::query!(
+            r#"
+            UPDATE download_jobs
+            SET status = 'queued', error = NULL, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND status = 'failed'
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected()
+            > 0;
+
+        if retried {
+            self.notify.notify_one();
+        }
+        Ok(retried)
+    }
+
+    async fn requeue_interrupted(&self) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE download_jobs
+            SET status = 'queued', updated_at = CURRENT_TIMESTAMP
+            WHERE status = 'running'
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Marks the oldest queued job as running and hands it to the caller.
+    async fn claim(&self) -> anyhow::Result<Option<DownloadJob>> {
+        let job = sqlx::query_as!(
+            DownloadJob,
+            r#"
+            UPDATE download_jobs
+            SET status = 'running', error = NULL, updated_at = CURRENT_TIMESTAMP
+            WHERE id = (
+                SELECT id FROM download_jobs WHERE status = 'queued' ORDER BY id LIMIT 1
+            )
+            RETURNING id as "id!", manga_id, source, chapter_url, chapter_number, sub_chapter,
+                      chapter_title,
+                      status as "status: JobStatus", pages_done, pages_total, error,
+                      created_at as "created_at: String", updated_at as "updated_at: String"
+            "#
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(job)
+    }
+
+    async fn run_worker(self) {
+        loop {
+            match self.claim().await {
+                Ok(Some(job)) => {
+                    let id = job.id;
+                    if let Err(e) = self.process(job).await {
+                        eprintln!("Download job {id} failed: {e:?}");
+                        if let Err(e) = self
+                            .finish(id, JobStatus::Failed, Some(e.to_string()))
+                            .await
+                        {
+                            eprintln!("Could not record failure of job {id}: {e:?}");
+                        }
+                    }
+                }
+                Ok(None) => {
+                    let _ = tokio::time::timeout(IDLE_POLL, self.notify.notified()).await;
+                }
+                Err(e) => {
+                    eprintln!("Could not claim download job: {e:?}");
+                    tokio::time::sleep(IDLE_POLL).await;
+                }
+            }
+        }
+    }
+
+    async fn process(&self, job: DownloadJob) -> anyhow::Result<()> {
+        let source = self.state.sources.get(&job.source)?;
+        let pages = source.pages(&job.chapter_url).await?;
+        if pages.is_empty() {
+            return Err(anyhow::anyhow!("No pages found"));
+        }
+        let pages_total = pages.len() as i64;
+
+        let chapter_dir = chapter_dir(job.chapter_number, job.sub_chapter);
+        let chapter_storage_path = format!("data/manga/{}/{}", job.manga_id, chapter_dir);
+        let full_chapter_path = self.state.image_dir.join(&chapter_storage_path);
+        // Pages collect here until the whole chapter is down, so a failed job never leaves
+        // a half-filled chapter directory behind and a retry picks up where it stopped.
+        // It belongs to the job, so two downloads of one chapter never share pages.
+        let staging_path = self.state.image_dir.join(format!(
+            "data/manga/{}/.staging/job_{}",
+            job.manga_id, job.id
+        ));
+        fs::create_dir_all(&staging_path).await?;
+
+        let mut missing = Vec::new();
+        for (idx, page) in pages.iter().enumerate() {
+            let ext = page.url.split('.').next_back().unwrap_or("jpg");
+            let file_path = staging_path.join(format!("{:03}.{}", idx + 1, ext));
+            if !page_is_complete(&file_path).await? {
+                missing.push((file_path, Url::parse(&page.url)?));
+            }
+        }
+
+        let mut pages_done = pages_total - missing.len() as i64;
+        self.set_progress(job.id, pages_done, pages_total).await?;
+
+        let headers = source.headers();
+        let fetcher = &self.state.fetcher;
+        let mut downloads = stream::iter(missing)
+            .map(|(file_path, url)| {
+                let headers = &headers;
+                async move {
+                    let bytes = fetcher.get(url.clone(), headers).await?.bytes().await?;
+                    if bytes.is_empty() {
+                        return Err(anyhow::anyhow!("Empty page image at {url}"));
+                    }
+                    write_page(&file_path, &bytes).await
+                }
+            })
+            .buffer_unordered(fetcher.page_concurrency());
+
+        while let Some(downloaded) = downloads.next().await {
+            downloaded?;
+            pages_done += 1;
+            self.set_progress(job.id, pages_done, pages_total).await?;
+        }
+
+        publish_chapter(&staging_path, &full_chapter_path).await?;
+
+        sqlx::query!(
+            "UPDATE manga SET updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+            job.manga_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO chapters
+                (manga_id, chapter_number, sub_chapter, title, page_count, storage_path, source_url)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(manga_id, chapter_number, sub_chapter) DO UPDATE SET
+                title = excluded.title,
+                page_count = excluded.page_count,
+                storage_path = excluded.storage_path,
+                source_url = excluded.source_url
+            "#,
+            job.manga_id,
+            job.chapter_number,
+            job.sub_chapter,
+            job.chapter_title,
+            pages_total,
+            chapter_storage_path,
+            job.chapter_url
+        )
+        .execute(&self.pool)
+        .await?;
+
+        self.finish(job.id, JobStatus::Completed, None).await
+    }
+
+    async fn set_progress(&self, id: i64, pages_done: i64, pages_total: i64) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE download_jobs
+            SET pages_done = ?, pages_total = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#,
+            pages_done,
+            pages_total,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Sending only fails when nobody is listening
+        let _ = self.events.send(JobEvent::Progress {
+            job_id: id,
+            pages_done,
+            pages_total,
+        });
+        Ok(())
+    }
+
+    async fn finish(
+        &self,
+        id: i64,
+        status: JobStatus,
+        error: Option<String>,
+    ) -> anyhow::Result<()> {
+        sqlx::query!(
+            "UPDATE download_jobs SET status = ?, error = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+            status,
+            error,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        let event = match status {
+            JobStatus::Failed => JobEvent::Failed {
+                job_id: id,
+                error: error.unwrap_or_default(),
+            },
+            _ => JobEvent::Completed { job_id: id },
+        };
+        let _ = self.events.send(event);
+        Ok(())
+    }
+}
+
+// Pages are only ever renamed into place, so any non-empty file under its final name is whole.
+async fn page_is_complete(path: &Path) -> anyhow::Result<bool> {
+    match fs::metadata(path).await {
+        Ok(metadata) => Ok(metadata.is_file() && metadata.len() > 0),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn write_page(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".part");
+    fs::write(&partial, bytes).await?;
+    fs::rename(&partial, path).await?;
+    Ok(())
+}
+
+// Swaps the finished staging directory in for the chapter, replacing any older copy.
+pub(crate) async fn publish_chapter(staging: &Path, chapter: &Path) -> anyhow::Result<()> {
+    if fs::try_exists(chapter).await? {
+        fs::remove_dir_all(chapter).await?;
+    }
+    if let Some(parent) = chapter.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::rename(staging, chapter).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrrrr::DEFAULT_SOURCE;
+    use crate::arrrrr::label::sub_chapter_of;
+    use crate::db;
+    use tempfile::TempDir;
+
+    async fn test_queue() -> (JobQueue, [TempDir; 2]) {
+        let kv_dir = TempDir::new().unwrap();
+        let image_dir = TempDir::new().unwrap();
+        let state = AppState::new(kv_dir.path().to_path_buf(), image_dir.path().to_path_buf());
+        (
+            JobQueue::new(db::test_pool().await, state),
+            [kv_dir, image_dir],
+        )
+    }
+
+    fn new_job(chapter_number: f64) -> NewJob {
+        NewJob {
+            manga_id: 105778,
+            source: DEFAULT_SOURCE.to_string(),
+            chapter_url: format!("https://mangapill.com/chapters/2-{chapter_number}"),
+            chapter_number,
+            sub_chapter: sub_chapter_of(chapter_number),
+            chapter_title: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_and_get() {
+        let (queue, _dirs) = test_queue().await;
+
+        let id = queue.enqueue(new_job(1.0)).await.unwrap();
+        let job = queue.get(id).await.unwrap().unwrap();
+
+        assert_eq!(job.status, JobStatus::Queued);
+        assert_eq!(job.pages_done, 0);
+        assert!(job.pages_total.is_none());
+        assert!(queue.get(id + 1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_claim_oldest_first() {
+        let (queue, _dirs) = test_queue().await;
+
+        let first = queue.enqueue(new_job(1.0)).await.unwrap();
+        let second = queue.enqueue(new_job(2.0)).await.unwrap();
+
+        let claimed = queue.claim().await.unwrap().unwrap();
+        assert_eq!(claimed.id, first);
+        assert_eq!(claimed.status, JobStatus::Running);
+
+        assert_eq!(queue.claim().await.unwrap().unwrap().id, second);
+        assert!(queue.claim().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pending_chapters_ignores_finished_jobs() {
+        let (queue, _dirs) = test_queue().await;
+
+        let done = queue.enqueue(new_job(1.0)).await.unwrap();
+        queue.enqueue(new_job(2.0)).await.unwrap();
+        queue.claim().await.unwrap().unwrap();
+        queue
+            .finish(done, JobStatus::Completed, None)
+            .await
+            .unwrap();
+        queue.enqueue(new_job(3.0)).await.unwrap();
+
+        let mut pending = queue.pending_chapters(105778).await.unwrap();
+        pending.sort_by(|a, b| a.0.total_cmp(&b.0));
+        assert_eq!(pending, vec![(2.0, 0), (3.0, 0)]);
+        assert!(queue.pending_chapters(1).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_requeue_interrupted_keeps_progress() {
+        let (queue, _dirs) = test_queue().await;
+
+        let id = queue.enqueue(new_job(1.0)).await.unwrap();
+        queue.claim().await.unwrap().unwrap();
+        sqlx::query!("UPDATE download_jobs SET pages_done = 7 WHERE id = ?", id)
+            .execute(&queue.pool)
+            .await
+            .unwrap();
+
+        queue.requeue_interrupted().await.unwrap();
+
+        let job = queue.claim().await.unwrap().unwrap();
+        assert_eq!(job.id, id);
+        assert_eq!(job.pages_done, 7);
+    }
+
+    #[tokio::test]
+    async fn test_finish_records_error() {
+        let (queue, _dirs) = test_queue().await;
+
+        let id = queue.enqueue(new_job(1.0)).await.unwrap();
+        queue.claim().await.unwrap().unwrap();
+        queue
+            .finish(id, JobStatus::Failed, Some("boom".to_string()))
+            .await
+            .unwrap();
+
+        let job = queue.get(id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.error.as_deref(), Some("boom"));
+        assert!(queue.claim().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_progress_and_completion() {
+        let (queue, _dirs) = test_queue().await;
+
+        let id = queue.enqueue(new_job(1.0)).await.unwrap();
+        let mut first = queue.subscribe();
+        let mut second = queue.subscribe();
+
+        queue.set_progress(id, 3, 10).await.unwrap();
+        queue.finish(id, JobStatus::Completed, None).await.unwrap();
+
+        for rx in [&mut first, &mut second] {
+            match rx.recv().await.unwrap() {
+                JobEvent::Progress {
+                    job_id,
+                    pages_done,
+                    pages_total,
+                } => assert_eq!((job_id, pages_done, pages_total), (id, 3, 10)),
+                other => panic!("unexpected event {other:?}"),
+            }
+            let done = rx.recv().await.unwrap();
+            assert_eq!(done.job_id(), id);
+            assert_eq!(done.name(), "completed");
+            assert!(done.is_terminal());
+        }
+
+        let job = queue.get(id).await.unwrap().unwrap();
+        assert_eq!((job.pages_done, job.pages_total), (3, Some(10)));
+    }
+
+    #[tokio::test]
+    async fn test_retry_only_failed_jobs() {
+        let (queue, _dirs) = test_queue().await;
+
+        let id = queue.enqueue(new_job(1.0)).await.unwrap();
+        assert!(!queue.retry(id).await.unwrap());
+
+        queue.claim().await.unwrap().unwrap();
+        queue
+            .finish(id, JobStatus::Failed, Some("timeout".to_string()))
+            .await
+            .unwrap();
+        assert!(queue.retry(id).await.unwrap());
+
+        let job = queue.get(id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Queued);
+        assert!(job.error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_page_is_complete() {
+        let dir = TempDir::new().unwrap();
+        let page = dir.path().join("001.jpg");
+
+        assert!(!page_is_complete(&page).await.unwrap());
+
+        fs::write(&page, b"").await.unwrap();
+        assert!(!page_is_complete(&page).await.unwrap());
+
+        write_page(&page, b"\xff\xd8\xff").await.unwrap();
+        assert!(page_is_complete(&page).await.unwrap());
+        assert!(
+            !fs::try_exists(dir.path().join("001.jpg.part"))
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_publish_chapter_replaces_old_copy() {
+        let dir = TempDir::new().unwrap();
+        let staging = dir.path().join(".staging/job_1");
+        let chapter = dir.path().join("chapter_1");
+
+        fs::create_dir_all(&staging).await.unwrap();
+        fs::write(staging.join("001.jpg"), b"new").await.unwrap();
+        fs::create_dir_all(&chapter).await.unwrap();
+        fs::write(chapter.join("002.jpg"), b"old").await.unwrap();
+
+        publish_chapter(&staging, &chapter).await.unwrap();
+
+        assert!(!fs::try_exists(&staging).await.unwrap());
+        assert_eq!(fs::read(chapter.join("001.jpg")).await.unwrap(), b"new");
+        assert!(!fs::try_exists(chapter.join("002.jpg")).await.unwrap());
+    }
+}
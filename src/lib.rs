@@ -2,8 +2,15 @@ pub mod anilist;
 pub mod api;
 pub mod arrrrr;
 pub mod db;
+pub mod fetcher;
+pub mod ingest;
+pub mod jobs;
 pub mod storage;
+pub mod sync;
 
+use anilist::{AniListClient, MetadataCache};
+use arrrrr::Sources;
+use fetcher::{Fetcher, FetcherConfig};
 use std::path::PathBuf;
 use storage::kv::KVStore;
 
@@ -11,13 +18,37 @@ use storage::kv::KVStore;
 pub struct AppState {
     pub kv_store: KVStore,
     pub image_dir: PathBuf,
+    pub fetcher: Fetcher,
+    pub sources: Sources,
+    pub anilist: AniListClient,
+    pub metadata: MetadataCache,
 }
 
 impl AppState {
     pub fn new(kv_dir: PathBuf, image_dir: PathBuf) -> Self {
+        let fetcher = Fetcher::new(FetcherConfig::from_env());
+        let kv_store = KVStore::new(kv_dir);
+        let anilist = AniListClient::new(&anilist::anilist_url());
         Self {
-            kv_store: KVStore::new(kv_dir),
+            metadata: MetadataCache::new(
+                kv_store.clone(),
+                anilist.clone(),
+                MetadataCache::ttl_from_env(),
+            ),
+            anilist,
+            kv_store,
             image_dir,
+            sources: Sources::builtin(&fetcher),
+            fetcher,
+        }
+    }
+
+    /// Points every AniList request, cached metadata included, at `anilist`.
+    pub fn with_anilist(self, anilist: AniListClient) -> Self {
+        Self {
+            metadata: self.metadata.with_client(anilist.clone()),
+            anilist,
+            ..self
         }
     }
 }
@@ -1,6 +1,8 @@
 use axum::Router;
 use dotenv::dotenv;
-use esfwee::{AppState, api, db};
+use esfwee::jobs::JobQueue;
+use esfwee::sync::AniListSync;
+use esfwee::{AppState, anilist, api, db};
 use sqlx::{Pool, Sqlite};
 use std::env;
 use std::path::PathBuf;
@@ -15,25 +17,32 @@ async fn main() {
     let state = AppState::new(kv_dir, image_dir);
     let pool = db::connect_db().await;
 
+    let workers = env::var("DOWNLOAD_WORKERS")
+        .ok()
+        .and_then(|w| w.parse().ok())
+        .unwrap_or(2);
+    let queue = JobQueue::new(pool.clone(), state.clone());
+    queue
+        .start(workers)
+        .await
+        .expect("Failed to start download workers");
+
+    anilist::start_refresh(pool.clone(), state.clone());
+
+    let sync = AniListSync::new(pool.clone(), state.anilist.clone());
+    sync.start();
+
     // Todo:
     // GET /manga
     // POST /manga
     // GET /manga/:id
     // DELETE /manga/:id
-    // PUT /manga/:id // Metadata
     // GET    /manga/:id/chapters       # List chapters
-    // GET    /chapters/:id             # Chapter details
     // GET    /chapters/:id/pages/:num  # Stream page image
     // POST   /chapters/:id/process     # Extract uploaded archive
-    // GET    /progress                 # All reading progress
-    // POST   /progress                 # Update progress
-    // GET    /progress/:manga_id       # Progress for specific manga
-    // POST   /sync/anilist             # Trigger full sync
-    // GET    /sync/status              # Sync status
-    // POST   /sync/manga/:id           # Sync specific manga
     //
     //
-    let app = get_router(state, pool);
+    let app = get_router(state, pool, queue, sync);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
 
@@ -41,6 +50,6 @@ async fn main() {
     axum::serve(listener, app).await.unwrap();
 }
 
-fn get_router(state: AppState, pool: Pool<Sqlite>) -> Router {
-    api::router(state, pool)
+fn get_router(state: AppState, pool: Pool<Sqlite>, queue: JobQueue, sync: AniListSync) -> Router {
+    api::router(state, pool, queue, sync)
 }
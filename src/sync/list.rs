@@ -0,0 +1,180 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use super::AniListSync;
+
+const LIST_QUERY: &str = r#"
+    query ($userId: Int) {
+        MediaListCollection(userId: $userId, type: MANGA) {
+            lists {
+                isCustomList
+                entries {
+                    mediaId
+                    status
+                    progress
+                    score
+                    updatedAt
+                    media {
+                        title {
+                            romaji
+                            english
+                        }
+                    }
+                }
+            }
+        }
+    }
+"#;
+
+/// A list an AniList entry sits in. "reading" and "plan_to_read" are accepted as
+/// aliases of `current` and `planning`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "UPPERCASE")]
+pub enum ListStatus {
+    #[serde(alias = "reading")]
+    Current,
+    #[serde(alias = "plan_to_read")]
+    Planning,
+    Completed,
+    Dropped,
+    Paused,
+    Repeating,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListEntry {
+    pub anilist_id: i64,
+    pub title: String,
+    pub status: ListStatus,
+    pub progress: i64,
+    pub score: Option<f64>,
+    pub anilist_updated_at: Option<i64>,
+    pub pulled_at: String,
+    /// Whether the series is stored locally.
+    pub in_library: bool,
+}
+
+#[derive(Deserialize)]
+struct ListData {
+    #[serde(rename = "MediaListCollection")]
+    collection: Collection,
+}
+
+#[derive(Deserialize)]
+struct Collection {
+    lists: Vec<List>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct List {
+    is_custom_list: bool,
+    entries: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    media_id: i64,
+    // Stored as AniList spells it, which is how `ListStatus` maps to the column
+    status: String,
+    progress: Option<i64>,
+    score: Option<f64>,
+    updated_at: Option<i64>,
+    media: Media,
+}
+
+#[derive(Deserialize)]
+struct Media {
+    title: Title,
+}
+
+#[derive(Deserialize)]
+struct Title {
+    romaji: Option<String>,
+    english: Option<String>,
+}
+
+impl AniListSync {
+    /// Replaces the local copy of the account's MANGA list. Returns how many entries it holds.
+    pub async fn pull_list(&self) -> anyhow::Result<usize> {
+        let account = sqlx::query!("SELECT access_token, user_id FROM anilist_account")
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow!("No AniList account linked"))?;
+
+        let data: ListData = self
+            .anilist
+            .with_token(&account.access_token)
+            .query(LIST_QUERY, serde_json::json!({ "userId": account.user_id }))
+            .await?;
+
+        // Custom lists repeat entries already in their status list
+        let entries: Vec<Entry> = data
+            .collection
+            .lists
+            .into_iter()
+            .filter(|l| !l.is_custom_list)
+            .flat_map(|l| l.entries)
+            .collect();
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!("DELETE FROM anilist_list")
+            .execute(&mut *tx)
+            .await?;
+
+        for entry in &entries {
+            let title = entry
+                .media
+                .title
+                .english
+                .as_ref()
+                .or(entry.media.title.romaji.as_ref())
+                .cloned()
+                .unwrap_or_else(|| entry.media_id.to_string());
+            let progress = entry.progress.unwrap_or(0);
+            // AniList reports unscored entries as 0
+            let score = entry.score.filter(|s| *s > 0.0);
+
+            sqlx::query!(
+                r#"
+                INSERT INTO anilist_list (anilist_id, title, status, progress, score, anilist_updated_at)
+                VALUES (?, ?, ?, ?, ?, ?)
+                ON CONFLICT(anilist_id) DO NOTHING
+                "#,
+                entry.media_id,
+                title,
+                entry.status,
+                progress,
+                score,
+                entry.updated_at
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(entries.len())
+    }
+
+    pub async fn list(&self, status: Option<ListStatus>) -> anyhow::Result<Vec<ListEntry>> {
+        let entries = sqlx::query_as!(
+            ListEntry,
+            r#"
+            SELECT l.anilist_id as "anilist_id!", l.title, l.status as "status: ListStatus",
+                   l.progress, l.score, l.anilist_updated_at, l.pulled_at as "pulled_at: String",
+                   m.anilist_id IS NOT NULL as "in_library!: bool"
+            FROM anilist_list l
+            LEFT JOIN manga m ON m.anilist_id = l.anilist_id
+            WHERE ?1 IS NULL OR l.status = ?1
+            ORDER BY l.anilist_updated_at DESC
+            "#,
+            status
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+}
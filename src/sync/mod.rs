@@ -0,0 +1,761 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use tokio::sync::Notify;
+
+use crate::anilist::{AniListClient, AniListError};
+
+mod list;
+
+pub use list::{ListEntry, ListStatus};
+
+// Pending entries are retried this often even without new progress to push.
+const IDLE_POLL: Duration = Duration::from_secs(30);
+
+// Pushes that fail this many times in a row are left for the user to retry.
+const MAX_ATTEMPTS: i64 = 3;
+
+const VIEWER_QUERY: &str = r#"
+    query {
+        Viewer {
+            id
+            name
+        }
+    }
+"#;
+
+const ENTRY_QUERY: &str = r#"
+    query ($userId: Int, $mediaId: Int) {
+        MediaList(userId: $userId, mediaId: $mediaId, type: MANGA) {
+            status
+            progress
+        }
+    }
+"#;
+
+// `status` is only sent for series not on the list yet; an existing entry keeps its own
+const SAVE_PROGRESS_MUTATION: &str = r#"
+    mutation ($mediaId: Int, $progress: Int, $status: MediaListStatus) {
+        SaveMediaListEntry(mediaId: $mediaId, progress: $progress, status: $status) {
+            id
+            progress
+        }
+    }
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum SyncState {
+    Pending,
+    Synced,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Account {
+    pub user_id: i64,
+    pub user_name: String,
+    pub added_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncEntry {
+    pub manga_id: i64,
+    pub anilist_id: Option<i64>,
+    pub title: String,
+    pub status: SyncState,
+    /// Chapter count AniList had after the last sync.
+    pub progress: Option<i64>,
+    pub attempts: i64,
+    pub error: Option<String>,
+    pub updated_at: String,
+    pub synced_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncStatus {
+    pub account: Option<Account>,
+    /// When the AniList list was last pulled, if ever.
+    pub list_pulled_at: Option<String>,
+    pub pending: Vec<SyncEntry>,
+    pub failed: Vec<SyncEntry>,
+}
+
+#[derive(Deserialize)]
+struct ViewerData {
+    #[serde(rename = "Viewer")]
+    viewer: Viewer,
+}
+
+#[derive(Deserialize)]
+struct Viewer {
+    id: i64,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct EntryData {
+    #[serde(rename = "MediaList")]
+    entry: RemoteEntry,
+}
+
+#[derive(Deserialize)]
+struct RemoteEntry {
+    progress: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct SaveData {
+    #[serde(rename = "SaveMediaListEntry")]
+    entry: SavedEntry,
+}
+
+#[derive(Deserialize)]
+struct SavedEntry {
+    progress: Option<i64>,
+}
+
+/// Pushes finished chapters to the linked AniList account from a background task.
+/// Each manga has at most one outstanding entry; the chapter count is worked out when it's sent.
+#[derive(Clone)]
+pub struct AniListSync {
+    pool: Pool<Sqlite>,
+    anilist: AniListClient,
+    notify: Arc<Notify>,
+}
+
+impl AniListSync {
+    pub fn new(pool: Pool<Sqlite>, anilist: AniListClient) -> Self {
+        Self {
+            pool,
+            anilist,
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn start(&self) {
+        let sync = self.clone();
+        tokio::spawn(async move { sync.run_worker().await });
+    }
+
+    /// Checks the token against AniList and stores it, replacing any previous account.
+    pub async fn set_token(&self, token: &str) -> anyhow::Result<Account> {
+        let data: ViewerData = self
+            .anilist
+            .with_token(token)
+            .query(VIEWER_QUERY, serde_json::json!({}))
+            .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO anilist_account (id, access_token, user_id, user_name)
+            VALUES (1, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                access_token = excluded.access_token,
+                user_id = excluded.user_id,
+                user_name = excluded.user_name,
+                added_at = CURRENT_TIMESTAMP
+            "#,
+            token,
+            data.viewer.id,
+            data.viewer.name
+        )
+        .execute(&self.pool)
+        .await?;
+
+        self.notify.notify_one();
+        self.account()
+            .await?
+            .ok_or_else(|| anyhow!("AniList account was not saved"))
+    }
+
+    /// Forgets the account. Outstanding entries wait until another one is linked.
+    pub async fn clear_token(&self) -> anyhow::Result<()> {
+        sqlx::query!("DELETE FROM anilist_account")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn account(&self) -> anyhow::Result<Option<Account>> {
+        let account = sqlx::query_as!(
+            Account,
+            r#"
+            SELECT user_id, user_name, added_at as "added_at: String"
+            FROM anilist_account
+            "#
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(account)
+    }
+
+    /// Schedules a push for one manga. Returns false when no account is linked or the
+    /// series has no AniList entry to push to.
+    pub async fn enqueue(&self, manga_id: i64) -> anyhow::Result<bool> {
+        if self.account().await?.is_none() {
+            return Ok(false);
+        }
+
+        let queued = sqlx::query!(
+            r#"
+            INSERT INTO anilist_sync (manga_id)
+            SELECT id FROM manga WHERE id = ? AND anilist_id IS NOT NULL
+            ON CONFLICT(manga_id) DO UPDATE SET
+                status = 'pending', attempts = 0, error = NULL, updated_at = CURRENT_TIMESTAMP
+            "#,
+            manga_id
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if queued == 0 {
+            return Ok(false);
+        }
+
+        self.notify.notify_one();
+        Ok(true)
+    }
+
+    /// Schedules a push for every linked manga whose finished chapters are ahead of its
+    /// entry in the pulled AniList list, or that isn't on the list at all.
+    pub async fn enqueue_all(&self) -> anyhow::Result<u64> {
+        if self.account().await?.is_none() {
+            return Ok(0);
+        }
+
+        let queued = sqlx::query!(
+            r#"
+            INSERT INTO anilist_sync (manga_id)
+            SELECT m.id FROM manga m
+            JOIN reading_progress p ON p.manga_id = m.id AND p.completed
+            JOIN chapters c ON c.id = p.chapter_id
+            LEFT JOIN anilist_list l ON l.anilist_id = m.anilist_id
+            WHERE m.anilist_id IS NOT NULL
+            GROUP BY m.id
+            HAVING l.progress IS NULL OR CAST(MAX(c.chapter_number) AS INTEGER) > l.progress
+            ON CONFLICT(manga_id) DO UPDATE SET
+                status = 'pending', attempts = 0, error = NULL, updated_at = CURRENT_TIMESTAMP
+            "#
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        self.notify.notify_one();
+        Ok(queued)
+    }
+
+    pub async fn status(&self) -> anyhow::Result<SyncStatus> {
+        let entries = sqlx::query_as!(
+            SyncEntry,
+            r#"
+            SELECT s.manga_id as "manga_id!", m.anilist_id, m.title, s.status as "status: SyncState",
+                   s.progress, s.attempts, s.error, s.updated_at as "updated_at: String",
+                   s.synced_at as "synced_at: String"
+            FROM anilist_sync s
+            JOIN manga m ON m.id = s.manga_id
+            WHERE s.status IN ('pending', 'failed')
+            ORDER BY s.updated_at DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let (pending, failed) = entries
+            .into_iter()
+            .partition(|e| e.status == SyncState::Pending);
+
+        let list_pulled_at = sqlx::query_scalar!(
+            r#"SELECT MAX(pulled_at) as "pulled_at: String" FROM anilist_list"#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(SyncStatus {
+            account: self.account().await?,
+            list_pulled_at,
+            pending,
+            failed,
+        })
+    }
+
+    /// Sends every pending entry once. Returns how many AniList accepted.
+    pub async fn sync_pending(&self) -> anyhow::Result<usize> {
+        let Some(account) =
+            sqlx::query!("SELECT access_token, user_id FROM anilist_account WHERE id = 1")
+                .fetch_optional(&self.pool)
+                .await?
+        else {
+            return Ok(0);
+        };
+
+        let pending = sqlx::query_scalar!(
+            r#"
+            SELECT manga_id as "manga_id!" FROM anilist_sync
+            WHERE status = 'pending'
+            ORDER BY updated_at
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut synced = 0;
+        for manga_id in pending {
+            let pushed = self
+                .push(&account.access_token, account.user_id, manga_id)
+                .await;
+            // One entry the database can't record doesn't hold up the rest
+            match self.record(manga_id, pushed).await {
+                Ok(true) => synced += 1,
+                Ok(false) => {}
+                Err(e) => eprintln!("Could not record AniList sync of {manga_id}: {e:?}"),
+            }
+        }
+
+        Ok(synced)
+    }
+
+    // Stores how a push went. Returns whether AniList accepted it.
+    async fn record(
+        &self,
+        manga_id: i64,
+        pushed: anyhow::Result<(i64, Option<i64>)>,
+    ) -> anyhow::Result<bool> {
+        match pushed {
+            Ok((anilist_id, progress)) => {
+                sqlx::query!(
+                    r#"
+                    UPDATE anilist_sync
+                    SET status = 'synced', progress = ?, attempts = 0, error = NULL,
+                        updated_at = CURRENT_TIMESTAMP, synced_at = CURRENT_TIMESTAMP
+                    WHERE manga_id = ?
+                    "#,
+                    progress,
+                    manga_id
+                )
+                .execute(&self.pool)
+                .await?;
+                // Keep the pulled list in step until the next full pull
+                if let Some(progress) = progress {
+                    sqlx::query!(
+                        "UPDATE anilist_list SET progress = ? WHERE anilist_id = ?",
+                        progress,
+                        anilist_id
+                    )
+                    .execute(&self.pool)
+                    .await?;
+                }
+                Ok(true)
+            }
+            Err(e) => {
+                let error = e.to_string();
+                sqlx::query!(
+                    r#"
+                    UPDATE anilist_sync
+                    SET attempts = attempts + 1, error = ?, updated_at = CURRENT_TIMESTAMP,
+                        status = CASE WHEN attempts + 1 >= ? THEN 'failed' ELSE 'pending' END
+                    WHERE manga_id = ?
+                    "#,
+                    error,
+                    MAX_ATTEMPTS,
+                    manga_id
+                )
+                .execute(&self.pool)
+                .await?;
+                Ok(false)
+            }
+        }
+    }
+
+    // AniList counts whole chapters, so 10.5 read means a progress of 10. Progress only
+    // ever moves forward: rereading an early chapter, or deleting a later one, leaves
+    // AniList as it is. Returns the AniList entry along with the progress it now has.
+    async fn push(
+        &self,
+        token: &str,
+        user_id: i64,
+        manga_id: i64,
+    ) -> anyhow::Result<(i64, Option<i64>)> {
+        let anilist_id = sqlx::query_scalar!("SELECT anilist_id FROM manga WHERE id = ?", manga_id)
+            .fetch_one(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow!("Series is not linked to AniList"))?;
+
+        let progress = sqlx::query_scalar!(
+            r#"
+            SELECT CAST(MAX(c.chapter_number) AS INTEGER) as "progress: i64"
+            FROM reading_progress p
+            JOIN chapters c ON c.id = p.chapter_id
+            WHERE p.manga_id = ? AND p.completed
+            "#,
+            manga_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let anilist = self.anilist.with_token(token);
+        let remote = match anilist
+            .query::<EntryData>(
+                ENTRY_QUERY,
+                serde_json::json!({ "userId": user_id, "mediaId": anilist_id }),
+            )
+            .await
+        {
+            Ok(data) => Some(data.entry.progress.unwrap_or(0)),
+            Err(AniListError::NotFound) => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        // Nothing finished here any more, e.g. after its chapters were deleted
+        let Some(progress) = progress else {
+            return Ok((anilist_id, remote));
+        };
+        let mut variables = serde_json::json!({ "mediaId": anilist_id, "progress": progress });
+        match remote {
+            Some(remote) if remote >= progress => return Ok((anilist_id, Some(remote))),
+            Some(_) => {}
+            None => variables["status"] = "CURRENT".into(),
+        }
+
+        let data: SaveData = anilist.query(SAVE_PROGRESS_MUTATION, variables).await?;
+
+        Ok((anilist_id, data.entry.progress))
+    }
+
+    async fn run_worker(self) {
+        loop {
+            if let Err(e) = self.sync_pending().await {
+                eprintln!("AniList sync failed: {e:?}");
+            }
+            let _ = tokio::time::timeout(IDLE_POLL, self.notify.notified()).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrrrr::serve_fixtures;
+    use crate::db;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::{Json, Router, routing::post};
+    use serde_json::{Value, json};
+    use std::sync::Mutex;
+
+    type Requests = Arc<Mutex<Vec<(Option<String>, Value)>>>;
+
+    // A GraphQL endpoint that answers Viewer, MediaListCollection, MediaList and
+    // SaveMediaListEntry, recording each request. `remote` is the MediaList entry,
+    // None when the series isn't on the list.
+    async fn stub(fail_saves: bool, remote: Option<Value>) -> (String, Requests) {
+        let requests: Requests = Arc::default();
+        let seen = requests.clone();
+        let router = Router::new().route(
+            "/",
+            post(move |headers: HeaderMap, Json(body): Json<Value>| {
+                let seen = seen.clone();
+                let remote = remote.clone();
+                async move {
+                    let auth = headers
+                        .get("authorization")
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string);
+                    let query = body["query"].as_str().unwrap_or_default().to_string();
+                    let progress = body["variables"]["progress"].clone();
+                    seen.lock().unwrap().push((auth, body));
+
+                    if query.contains("MediaListCollection") {
+                        let list: Value =
+                            serde_json::from_str(include_str!("fixtures/media_list.json")).unwrap();
+                        (StatusCode::OK, Json(list))
+                    } else if query.contains("MediaList(") {
+                        match remote {
+                            Some(entry) => (
+                                StatusCode::OK,
+                                Json(json!({ "data": { "MediaList": entry } })),
+                            ),
+                            None => (
+                                StatusCode::NOT_FOUND,
+                                Json(json!({
+                                    "data": { "MediaList": null },
+                                    "errors": [{ "message": "Not Found.", "status": 404 }]
+                                })),
+                            ),
+                        }
+                    } else if query.contains("Viewer") {
+                        (
+                            StatusCode::OK,
+                            Json(json!({ "data": { "Viewer": { "id": 42, "name": "reader" } } })),
+                        )
+                    } else if fail_saves {
+                        (
+                            StatusCode::UNAUTHORIZED,
+                            Json(
+                                json!({ "data": null, "errors": [{ "message": "Invalid token" }] }),
+                            ),
+                        )
+                    } else {
+                        (
+                            StatusCode::OK,
+                            Json(json!({
+                                "data": { "SaveMediaListEntry": { "id": 1, "progress": progress } }
+                            })),
+                        )
+                    }
+                }
+            }),
+        );
+        (serve_fixtures(router).await, requests)
+    }
+
+    async fn seeded_sync(endpoint: &str) -> AniListSync {
+        let pool = db::test_pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO manga (id, anilist_id, title, storage_path) VALUES
+                (30013, 30013, 'One Piece', 'data/manga/30013'),
+                (30014, NULL, 'Doujin', 'data/manga/30014');
+            INSERT INTO chapters (id, manga_id, chapter_number, page_count, storage_path) VALUES
+                (1, 30013, 1, 3, 'data/manga/30013/chapter_1'),
+                (2, 30013, 2.5, 3, 'data/manga/30013/chapter_2.5'),
+                (3, 30014, 1, 3, 'data/manga/30014/chapter_1');
+            INSERT INTO reading_progress (manga_id, chapter_id, page, completed) VALUES
+                (30013, 1, 3, 1),
+                (30013, 2, 3, 1),
+                (30014, 3, 3, 1);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        AniListSync::new(pool, AniListClient::new(endpoint))
+    }
+
+    // One Piece as the pulled list fixture has it
+    fn reading() -> Option<Value> {
+        Some(json!({ "status": "CURRENT", "progress": 1 }))
+    }
+
+    fn saves(requests: &Requests) -> Vec<Value> {
+        requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, body)| {
+                body["query"]
+                    .as_str()
+                    .unwrap()
+                    .contains("SaveMediaListEntry")
+            })
+            .map(|(_, body)| body["variables"].clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_needs_account() {
+        let (endpoint, requests) = stub(false, reading()).await;
+        let sync = seeded_sync(&endpoint).await;
+
+        assert!(!sync.enqueue(30013).await.unwrap());
+        assert_eq!(sync.sync_pending().await.unwrap(), 0);
+        assert!(requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pushes_progress() {
+        let (endpoint, requests) = stub(false, reading()).await;
+        let sync = seeded_sync(&endpoint).await;
+
+        let account = sync.set_token("secret").await.unwrap();
+        assert_eq!(account.user_name, "reader");
+        assert!(sync.enqueue(30013).await.unwrap());
+        assert_eq!(sync.status().await.unwrap().pending.len(), 1);
+
+        assert_eq!(sync.sync_pending().await.unwrap(), 1);
+
+        // Already on the list, so its status is left alone
+        assert_eq!(
+            saves(&requests),
+            vec![json!({ "mediaId": 30013, "progress": 2 })]
+        );
+        let (auth, _) = requests.lock().unwrap().pop().unwrap();
+        assert_eq!(auth.as_deref(), Some("Bearer secret"));
+
+        let status = sync.status().await.unwrap();
+        assert!(status.pending.is_empty() && status.failed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_never_lowers_anilist_progress() {
+        let remote = json!({ "status": "COMPLETED", "progress": 120 });
+        let (endpoint, requests) = stub(false, Some(remote)).await;
+        let sync = seeded_sync(&endpoint).await;
+        sync.set_token("secret").await.unwrap();
+        sync.enqueue(30013).await.unwrap();
+
+        assert_eq!(sync.sync_pending().await.unwrap(), 1);
+
+        // Neither progress nor status was sent, and the entry counts as up to date
+        assert!(saves(&requests).is_empty());
+        let status = sync.status().await.unwrap();
+        assert!(status.pending.is_empty() && status.failed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_adds_missing_entry_as_reading() {
+        let (endpoint, requests) = stub(false, None).await;
+        let sync = seeded_sync(&endpoint).await;
+        sync.set_token("secret").await.unwrap();
+        sync.enqueue(30013).await.unwrap();
+
+        assert_eq!(sync.sync_pending().await.unwrap(), 1);
+
+        assert_eq!(
+            saves(&requests),
+            vec![json!({ "mediaId": 30013, "progress": 2, "status": "CURRENT" })]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_entry_with_nothing_to_push_leaves_others_syncing() {
+        let (endpoint, requests) = stub(false, None).await;
+        let sync = seeded_sync(&endpoint).await;
+        sync.set_token("secret").await.unwrap();
+        // One Piece is still in the pulled list, but gone from AniList and unread here
+        sync.pull_list().await.unwrap();
+        sqlx::query(
+            r#"
+            DELETE FROM reading_progress WHERE manga_id = 30013;
+            INSERT INTO manga (id, anilist_id, title, storage_path)
+                VALUES (30002, 30002, 'Berserk', 'data/manga/30002');
+            INSERT INTO chapters (id, manga_id, chapter_number, page_count, storage_path)
+                VALUES (4, 30002, 1, 3, 'data/manga/30002/chapter_1');
+            INSERT INTO reading_progress (manga_id, chapter_id, page, completed) VALUES (30002, 4, 3, 1);
+            "#,
+        )
+        .execute(&sync.pool)
+        .await
+        .unwrap();
+        sync.enqueue(30013).await.unwrap();
+        sync.enqueue(30002).await.unwrap();
+
+        assert_eq!(sync.sync_pending().await.unwrap(), 2);
+
+        assert_eq!(
+            saves(&requests),
+            vec![json!({ "mediaId": 30002, "progress": 1, "status": "CURRENT" })]
+        );
+        let status = sync.status().await.unwrap();
+        assert!(status.pending.is_empty() && status.failed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_skips_series_without_anilist_entry() {
+        let (endpoint, _) = stub(false, reading()).await;
+        let sync = seeded_sync(&endpoint).await;
+        sync.set_token("secret").await.unwrap();
+
+        assert!(!sync.enqueue(30014).await.unwrap());
+        assert_eq!(sync.enqueue_all().await.unwrap(), 1);
+        assert_eq!(sync.status().await.unwrap().pending[0].manga_id, 30013);
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_all_skips_series_anilist_is_ahead_on() {
+        let (endpoint, _) = stub(false, reading()).await;
+        let sync = seeded_sync(&endpoint).await;
+        sync.set_token("secret").await.unwrap();
+        // Only the prologue is read here, while AniList is at chapter 1
+        sqlx::query(
+            "DELETE FROM reading_progress WHERE chapter_id = 2; UPDATE chapters SET chapter_number = 0 WHERE id = 1",
+        )
+        .execute(&sync.pool)
+        .await
+        .unwrap();
+
+        sync.pull_list().await.unwrap();
+
+        assert_eq!(sync.enqueue_all().await.unwrap(), 0);
+        assert!(sync.status().await.unwrap().pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let (endpoint, _) = stub(true, reading()).await;
+        let sync = seeded_sync(&endpoint).await;
+        sync.set_token("expired").await.unwrap();
+        sync.enqueue(30013).await.unwrap();
+
+        for _ in 0..MAX_ATTEMPTS {
+            assert_eq!(sync.sync_pending().await.unwrap(), 0);
+        }
+
+        let status = sync.status().await.unwrap();
+        assert!(status.pending.is_empty());
+        assert_eq!(status.failed[0].attempts, MAX_ATTEMPTS);
+        assert_eq!(
+            status.failed[0].error.as_deref(),
+            Some("AniList API returned error: Invalid token")
+        );
+
+        // Enqueuing again gives it a fresh set of attempts
+        sync.enqueue(30013).await.unwrap();
+        assert_eq!(sync.status().await.unwrap().pending[0].attempts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_pull_list() {
+        let (endpoint, requests) = stub(false, reading()).await;
+        let sync = seeded_sync(&endpoint).await;
+        assert!(sync.pull_list().await.is_err());
+
+        sync.set_token("secret").await.unwrap();
+        assert_eq!(sync.pull_list().await.unwrap(), 2);
+        let (_, body) = requests.lock().unwrap().pop().unwrap();
+        assert_eq!(body["variables"], json!({ "userId": 42 }));
+
+        let all = sync.list(None).await.unwrap();
+        assert_eq!(all.len(), 2);
+
+        let reading = sync.list(Some(ListStatus::Current)).await.unwrap();
+        assert_eq!(reading.len(), 1);
+        assert_eq!(reading[0].title, "One Piece");
+        assert_eq!(reading[0].progress, 1);
+        assert!(reading[0].in_library);
+
+        let planning = sync.list(Some(ListStatus::Planning)).await.unwrap();
+        assert_eq!(planning[0].title, "Chainsaw Man");
+        assert_eq!(planning[0].score, None);
+        assert!(!planning[0].in_library);
+
+        assert!(sync.status().await.unwrap().list_pulled_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_push_updates_pulled_list() {
+        let (endpoint, _) = stub(false, reading()).await;
+        let sync = seeded_sync(&endpoint).await;
+        sync.set_token("secret").await.unwrap();
+        sync.pull_list().await.unwrap();
+
+        sync.enqueue(30013).await.unwrap();
+        sync.sync_pending().await.unwrap();
+
+        let reading = sync.list(Some(ListStatus::Current)).await.unwrap();
+        assert_eq!(reading[0].progress, 2);
+    }
+
+    #[test]
+    fn test_list_status_aliases() {
+        let status: ListStatus = serde_json::from_str("\"reading\"").unwrap();
+        assert_eq!(status, ListStatus::Current);
+        let status: ListStatus = serde_json::from_str("\"plan_to_read\"").unwrap();
+        assert_eq!(status, ListStatus::Planning);
+    }
+}